@@ -38,7 +38,8 @@ CREATE TABLE `instances` (
   `from_key` varchar(256) NOT NULL COMMENT 'meta|id|para|sta_ver',
   PRIMARY KEY (`meta`,`ins_id`,`para`,`state_version`),
   UNIQUE KEY `instances_un` (`from_key`,`meta`,`ins_id`,`para`),
-  KEY `instances_create_time_IDX` (`create_time`) USING BTREE
+  KEY `instances_create_time_IDX` (`create_time`) USING BTREE,
+  KEY `instances_meta_time_IDX` (`meta`,`create_time`,`ins_id`) USING BTREE
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 create TABLE `task` (
@@ -51,9 +52,12 @@ create TABLE `task` (
 	`create_time`	DATETIME NOT NULL,
 	`execute_time`	DATETIME NOT NULL,
 	`retried_times`	SMALLINT NOT NULL,
+	`lease_owner`	VARCHAR ( 128 ) NOT NULL DEFAULT '' COMMENT 'the retry worker which loaded this task',
+	`lease_expire`	DATETIME NULL,
 	UNIQUE KEY `task_un` (`task_key`,`task_type`,`task_for`),
 	PRIMARY KEY(`task_id`),
-	KEY `task_create_time_IDX` (`create_time`,`task_state`) USING BTREE
+	KEY `task_create_time_IDX` (`create_time`,`task_state`) USING BTREE,
+	KEY `task_lease_IDX` (`lease_owner`,`lease_expire`) USING BTREE
 )ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 create TABLE `task_error` (
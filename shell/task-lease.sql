@@ -0,0 +1,7 @@
+```mysql
+-- upgrade the `task` table for the retry workers to lease tasks
+alter table task add column `lease_owner` VARCHAR ( 128 ) NOT NULL DEFAULT '' COMMENT 'the retry worker which loaded this task';
+alter table task add column `lease_expire` DATETIME NULL;
+alter table task add KEY `task_lease_IDX` (`lease_owner`,`lease_expire`) USING BTREE;
+
+```
@@ -1,9 +1,14 @@
+use nature::common::prepare_config;
 use nature::system::sys_init;
 
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
-    sys_init().await
+    match prepare_config() {
+        Ok(true) => sys_init().await,
+        Ok(false) => Ok(()),
+        Err(e) => {
+            eprintln!("configuration error: {}", e);
+            std::process::exit(1)
+        }
+    }
 }
-
-
-
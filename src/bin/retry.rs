@@ -1,6 +1,14 @@
+use nature::common::prepare_config;
 use nature::retry::start;
 
 #[tokio::main]
 pub async fn main() {
-    start().await
-}
\ No newline at end of file
+    match prepare_config() {
+        Ok(true) => start().await,
+        Ok(false) => (),
+        Err(e) => {
+            eprintln!("configuration error: {}", e);
+            std::process::exit(1)
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 
+use filter::filter;
+use group_by::group_by;
+use map::map as map_converter;
 use merge::merge;
 use scatter::scatter;
 use time_range::time_range;
 
 /// built-in xecutor
-use crate::common::{NatureError, Result};
+use crate::common::{Executor, NatureError, Protocol, Result};
 use crate::task::Execute;
 
 lazy_static! {
@@ -21,6 +24,12 @@ fn init_builtin() -> HashMap<String, &'static Execute> {
     map.insert("merge".to_string(), one);
     let one: &Execute = &(time_range as Execute);
     map.insert("time_range".to_string(), one);
+    let one: &Execute = &(group_by as Execute);
+    map.insert("group_by".to_string(), one);
+    let one: &Execute = &(map_converter as Execute);
+    map.insert("map".to_string(), one);
+    let one: &Execute = &(filter as Execute);
+    map.insert("filter".to_string(), one);
     map
 }
 
@@ -33,12 +42,20 @@ impl BuiltIn {
             None => Err(NatureError::VerifyError(format!("not exists built-in executor for name : {}", name))),
         }
     }
+
+    /// whether the executor is a `time_range` session window
+    pub fn is_session(executor: &Executor) -> bool {
+        executor.protocol == Protocol::BuiltIn && executor.url == "time_range" && time_range::is_session(&executor.settings)
+    }
 }
 
 // mod dimension_splitter;
 mod scatter;
 mod merge;
 mod time_range;
+mod group_by;
+mod map;
+mod filter;
 
 #[cfg(test)]
 mod test {
@@ -50,4 +67,18 @@ mod test {
         let rtn = BuiltIn::get("scatter");
         assert_eq!(rtn.is_ok(), true);
     }
+
+    #[test]
+    fn is_session_test() {
+        let mut executor = Executor {
+            protocol: Protocol::BuiltIn,
+            url: "time_range".to_string(),
+            settings: r#"{"unit":"m","session_gap":30}"#.to_string(),
+        };
+        assert!(BuiltIn::is_session(&executor));
+        executor.settings = r#"{"unit":"m","value":5}"#.to_string();
+        assert!(!BuiltIn::is_session(&executor));
+        executor.url = "merge".to_string();
+        assert!(!BuiltIn::is_session(&executor));
+    }
 }
\ No newline at end of file
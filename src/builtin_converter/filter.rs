@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
+use serde_json::Value;
+
+use crate::builtin_converter::merge::Num;
+use crate::common::{ConverterParameter, ConverterReturned, Instance, is_default};
+
+/// Forward the upstream `Instance` unchanged when the conditions matched,
+/// otherwise nothing will be returned and the task will be finished.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+struct Setting {
+    conditions: Vec<Condition>,
+    /// true: matched if any condition matched, false: all conditions must be matched
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    any: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Condition {
+    /// json pointer of the upstream content, for example "/order/amount"
+    field: String,
+    op: Op,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Op {
+    Eq(Value),
+    Ne(Value),
+    Gt(Value),
+    Ge(Value),
+    Lt(Value),
+    Le(Value),
+    /// equals to one of them
+    In(Vec<Value>),
+    /// `min` <= value < `max`, the absent bound is unlimited
+    Range {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        min: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        max: Option<Value>,
+    },
+    /// regular expression, number and bool will be matched as string
+    Regex(Pattern),
+    Exists,
+    NotExists,
+}
+
+/// A regular expression compiled when the setting is parsed
+#[derive(Debug)]
+struct Pattern(Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+        let pattern = String::deserialize(deserializer)?;
+        match Regex::new(&pattern) {
+            Ok(regex) => Ok(Pattern(regex)),
+            Err(e) => Err(D::Error::custom(format!("builtin-filter : regex error: {}", e)))
+        }
+    }
+}
+
+pub fn filter(input: &ConverterParameter) -> ConverterReturned {
+    // get setting
+    let cfg = match serde_json::from_str::<Setting>(&input.cfg) {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            warn!("error setting: {}", &input.cfg);
+            return ConverterReturned::LogicalError { msg: err.to_string() };
+        }
+    };
+    let content = match serde_json::from_str::<Value>(&input.from.content) {
+        Ok(content) => content,
+        Err(e) => {
+            let msg = format!("builtin-filter : instance content error: {}", e);
+            warn!("{}, content: {}", msg, input.from.content);
+            return ConverterReturned::LogicalError { msg };
+        }
+    };
+    if !is_matched(&cfg, &content) {
+        debug!("builtin-filter : instance filtered, meta: {}, id: {}", input.from.meta, input.from.id);
+        return ConverterReturned::Instances { ins: vec![] };
+    }
+    let mut ins = Instance::default();
+    ins.id = input.from.id;
+    ins.para = input.from.para.clone();
+    ins.content = input.from.content.clone();
+    ins.context = input.from.context.clone();
+    ins.sys_context = input.from.sys_context.clone();
+    ins.states = input.from.states.clone();
+    ConverterReturned::Instances { ins: vec![ins] }
+}
+
+fn is_matched(cfg: &Setting, content: &Value) -> bool {
+    for one in &cfg.conditions {
+        let matched = one.check(content);
+        if cfg.any && matched {
+            return true;
+        }
+        if !cfg.any && !matched {
+            return false;
+        }
+    }
+    // all matched for `all` mode, or none matched for `any` mode
+    !cfg.any || cfg.conditions.is_empty()
+}
+
+impl Condition {
+    fn check(&self, content: &Value) -> bool {
+        let value = content.pointer(&self.field);
+        let value = match (&self.op, value) {
+            (Op::Exists, v) => return v.is_some(),
+            (Op::NotExists, v) => return v.is_none(),
+            (_, None) => return false,
+            (_, Some(v)) => v,
+        };
+        match &self.op {
+            Op::Eq(v) => equal(value, v),
+            Op::Ne(v) => !equal(value, v),
+            Op::Gt(v) => compare(value, v) == Some(Ordering::Greater),
+            Op::Ge(v) => matches!(compare(value, v), Some(Ordering::Greater) | Some(Ordering::Equal)),
+            Op::Lt(v) => compare(value, v) == Some(Ordering::Less),
+            Op::Le(v) => matches!(compare(value, v), Some(Ordering::Less) | Some(Ordering::Equal)),
+            Op::In(list) => list.iter().any(|one| equal(value, one)),
+            Op::Range { min, max } => {
+                let ge_min = match min {
+                    None => true,
+                    Some(min) => matches!(compare(value, min), Some(Ordering::Greater) | Some(Ordering::Equal))
+                };
+                let lt_max = match max {
+                    None => true,
+                    Some(max) => compare(value, max) == Some(Ordering::Less)
+                };
+                ge_min && lt_max
+            }
+            Op::Regex(Pattern(regex)) => match value {
+                Value::String(s) => regex.is_match(s),
+                Value::Number(n) => regex.is_match(&n.to_string()),
+                Value::Bool(b) => regex.is_match(&b.to_string()),
+                _ => false
+            },
+            Op::Exists | Op::NotExists => unreachable!(),
+        }
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    a == b || compare(a, b) == Some(Ordering::Equal)
+}
+
+/// only number to number and string to string can be compared
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => {
+            let a = serde_json::from_value::<Num>(a.clone()).ok()?;
+            let b = serde_json::from_value::<Num>(b.clone()).ok()?;
+            a.partial_cmp(&b)
+        }
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(cfg: &str) -> ConverterReturned {
+        let input = ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.id = 5;
+                ins.para = "a/b".to_string();
+                ins.content = r#"{"amount":150,"rate":0.5,"city":"beijing","tags":["x"],"vip":true}"#.to_string();
+                ins.context.insert("k".to_string(), "v".to_string());
+                ins.sys_context.insert("loop.next".to_string(), "3".to_string());
+                ins.states.insert("paid".to_string());
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: cfg.to_string(),
+        };
+        filter(&input)
+    }
+
+    fn matched(cfg: &str) -> bool {
+        match run(cfg) {
+            ConverterReturned::Instances { ins } => !ins.is_empty(),
+            _ => panic!("should return instances")
+        }
+    }
+
+    #[test]
+    fn forward_test() {
+        match run(r#"{"conditions":[{"field":"/amount","op":{"Gt":100}}]}"#) {
+            ConverterReturned::Instances { ins } => {
+                assert_eq!(ins.len(), 1);
+                assert_eq!(ins[0].id, 5);
+                assert_eq!(ins[0].para, "a/b");
+                assert!(ins[0].content.contains("beijing"));
+                assert_eq!(ins[0].context.get("k").unwrap(), "v");
+                assert_eq!(ins[0].sys_context.get("loop.next").unwrap(), "3");
+                assert!(ins[0].states.contains("paid"));
+            }
+            _ => panic!("should return instances")
+        }
+    }
+
+    #[test]
+    fn compare_test() {
+        assert!(matched(r#"{"conditions":[{"field":"/amount","op":{"Eq":150.0}}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/amount","op":{"Ne":150}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/amount","op":{"Le":150}}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/rate","op":{"Lt":0.5}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/rate","op":{"Ge":0.5}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/city","op":{"Gt":"a"}}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/city","op":{"Gt":1}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/vip","op":{"Eq":true}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/city","op":{"In":["shanghai","beijing"]}}]}"#));
+    }
+
+    #[test]
+    fn range_test() {
+        assert!(matched(r#"{"conditions":[{"field":"/amount","op":{"Range":{"min":100,"max":200}}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/amount","op":{"Range":{"min":150}}}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/amount","op":{"Range":{"max":150}}}]}"#));
+    }
+
+    #[test]
+    fn regex_test() {
+        assert!(matched(r#"{"conditions":[{"field":"/city","op":{"Regex":"^bei"}}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/amount","op":{"Regex":"^1\\d+$"}}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/tags","op":{"Regex":"x"}}]}"#));
+        match run(r#"{"conditions":[{"field":"/city","op":{"Regex":"("}}]}"#) {
+            ConverterReturned::LogicalError { .. } => (),
+            _ => panic!("should return error")
+        }
+    }
+
+    #[test]
+    fn exists_and_mode_test() {
+        assert!(matched(r#"{"conditions":[{"field":"/tags/0","op":"Exists"}]}"#));
+        assert!(matched(r#"{"conditions":[{"field":"/none","op":"NotExists"}]}"#));
+        assert!(!matched(r#"{"conditions":[{"field":"/none","op":{"Eq":1}}]}"#));
+        let both = r#"[{"field":"/amount","op":{"Gt":1000}},{"field":"/vip","op":{"Eq":true}}]"#;
+        assert!(!matched(&format!(r#"{{"conditions":{}}}"#, both)));
+        assert!(matched(&format!(r#"{{"conditions":{},"any":true}}"#, both)));
+        assert!(matched(r#"{"conditions":[]}"#));
+        assert!(matched(r#"{"conditions":[],"any":true}"#));
+    }
+
+    #[test]
+    fn error_test() {
+        match run("") {
+            ConverterReturned::LogicalError { .. } => (),
+            _ => panic!("should return error")
+        }
+    }
+}
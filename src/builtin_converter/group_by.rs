@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::builtin_converter::merge::{Num, NumType};
+use crate::common::{ConverterParameter, ConverterReturned, get_para_and_key_from_para, Instance, is_default, NatureError, Result};
+
+/// `Instance.content` must be a json array, each item will be put into a group,
+/// and each group will generate a downstream `Instance` whose para is the group key.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Setting {
+    /// how to get the group key from the item, it's required
+    by: GroupBy,
+    /// json pointer of the value to be reduced, for example "/amount", default is the whole item.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    value: String,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    reduce: Reduce,
+    /// the type of the value used to `Sum`, `Min` and `Max`
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    num_type: NumType,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+enum GroupBy {
+    /// json pointer of the item, the value will be the group key, for example "/city"
+    Field(String),
+    /// the field which is a para-like string, and the `part` of which will be the group key
+    Para { field: String, part: Vec<u8> },
+}
+
+impl Setting {
+    fn check(&self) -> Result<()> {
+        let field = match &self.by {
+            GroupBy::Field(f) => f,
+            GroupBy::Para { field, .. } => field,
+        };
+        if field.is_empty() {
+            return Err(NatureError::VerifyError("builtin-group_by : the field of `by` can't be empty".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+enum Reduce {
+    Sum,
+    Count,
+    Min,
+    Max,
+    /// put the values into a json array
+    #[default]
+    Concat,
+}
+
+pub fn group_by(input: &ConverterParameter) -> ConverterReturned {
+    // get setting
+    let cfg = match serde_json::from_str::<Setting>(&input.cfg) {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            warn!("error setting: {}", &input.cfg);
+            return ConverterReturned::LogicalError { msg: err.to_string() };
+        }
+    };
+    if let Err(err) = cfg.check() {
+        return ConverterReturned::LogicalError { msg: err.to_string() };
+    }
+    let items = match serde_json::from_str::<Vec<Value>>(&input.from.content) {
+        Ok(items) => items,
+        Err(e) => {
+            let msg = format!("builtin-group_by : instance content error: {}", e);
+            warn!("{}, content: {}", msg, input.from.content);
+            return ConverterReturned::LogicalError { msg };
+        }
+    };
+    // group
+    let mut groups: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for item in items {
+        let key = match group_key(&cfg.by, &item) {
+            Ok(key) => key,
+            Err(e) => return ConverterReturned::LogicalError { msg: e.to_string() }
+        };
+        let value = match item.pointer(&cfg.value) {
+            Some(v) => v.clone(),
+            None => {
+                let msg = format!("builtin-group_by : can't find value {} from item: {}", cfg.value, item);
+                return ConverterReturned::LogicalError { msg };
+            }
+        };
+        groups.entry(key).or_default().push(value);
+    }
+    // reduce
+    let mut rtn: Vec<Instance> = Vec::with_capacity(groups.len());
+    for (key, values) in groups {
+        let mut ins = Instance::default();
+        ins.content = match reduce(&cfg, values) {
+            Ok(content) => content,
+            Err(e) => return ConverterReturned::LogicalError { msg: e.to_string() }
+        };
+        ins.para = key;
+        rtn.push(ins);
+    }
+    ConverterReturned::Instances { ins: rtn }
+}
+
+fn group_key(by: &GroupBy, item: &Value) -> Result<String> {
+    let field = match by {
+        GroupBy::Field(f) => f,
+        GroupBy::Para { field, .. } => field,
+    };
+    let key = match item.pointer(field) {
+        Some(Value::String(s)) => s.to_string(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        _ => {
+            let msg = format!("builtin-group_by : can't get group key {} from item: {}", field, item);
+            return Err(NatureError::VerifyError(msg));
+        }
+    };
+    match by {
+        GroupBy::Field(_) => Ok(key),
+        GroupBy::Para { part, .. } => Ok(get_para_and_key_from_para(&key, part)?.0),
+    }
+}
+
+fn reduce(cfg: &Setting, values: Vec<Value>) -> Result<String> {
+    let rtn = match cfg.reduce {
+        Reduce::Count => values.len().to_string(),
+        Reduce::Concat => serde_json::to_string(&values)?,
+        Reduce::Sum => {
+            let mut sum = Num::zero(&cfg.num_type);
+            for v in values {
+                sum = sum.checked_add(to_num(v, &cfg.num_type)?)?;
+            }
+            sum.to_string()
+        }
+        Reduce::Min | Reduce::Max => {
+            let mut rtn: Option<Num> = None;
+            for v in values {
+                let v = to_num(v, &cfg.num_type)?;
+                rtn = match rtn {
+                    Some(o) if (cfg.reduce == Reduce::Min && o <= v) || (cfg.reduce == Reduce::Max && o >= v) => Some(o),
+                    _ => Some(v)
+                };
+            }
+            match rtn {
+                Some(num) => num.to_string(),
+                None => Num::zero(&cfg.num_type).to_string()
+            }
+        }
+    };
+    Ok(rtn)
+}
+
+fn to_num(value: Value, num_type: &NumType) -> Result<Num> {
+    match serde_json::from_value::<Num>(value.clone()) {
+        Ok(num) => num.to_type(num_type),
+        Err(_) => Err(NatureError::VerifyError(format!("builtin-group_by : the value is not a number: {}", value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn param(content: &str, cfg: &str) -> ConverterParameter {
+        ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.content = content.to_string();
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: cfg.to_string(),
+        }
+    }
+
+    fn result(input: &ConverterParameter) -> Vec<(String, String)> {
+        match group_by(input) {
+            ConverterReturned::Instances { ins } => ins.into_iter().map(|one| (one.para.clone(), one.content.clone())).collect(),
+            _ => panic!("should return instances")
+        }
+    }
+
+    const ORDERS: &str = r#"[
+        {"city":"bj","item":"shop1/apple","amount":"1.50"},
+        {"city":"sh","item":"shop2/pear","amount":2},
+        {"city":"bj","item":"shop1/pear","amount":"3.25"}
+    ]"#;
+
+    #[test]
+    fn concat_test() {
+        let input = param(r#"[{"city":"bj","n":1},{"city":"sh","n":2},{"city":"bj","n":3}]"#, r#"{"by":{"Field":"/city"},"value":"/n"}"#);
+        assert_eq!(result(&input), vec![
+            ("bj".to_string(), "[1,3]".to_string()),
+            ("sh".to_string(), "[2]".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn sum_test() {
+        let input = param(ORDERS, r#"{"by":{"Field":"/city"},"value":"/amount","reduce":"Sum","num_type":{"Decimal":2}}"#);
+        assert_eq!(result(&input), vec![
+            ("bj".to_string(), "4.75".to_string()),
+            ("sh".to_string(), "2.00".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn para_part_test() {
+        let input = param(ORDERS, r#"{"by":{"Para":{"field":"/item","part":[0]}},"value":"/amount","reduce":"Count"}"#);
+        assert_eq!(result(&input), vec![
+            ("shop1".to_string(), "2".to_string()),
+            ("shop2".to_string(), "1".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn min_max_test() {
+        let input = param(ORDERS, r#"{"by":{"Field":"/city"},"value":"/amount","reduce":"Max","num_type":"Float"}"#);
+        assert_eq!(result(&input), vec![
+            ("bj".to_string(), "3.25".to_string()),
+            ("sh".to_string(), "2".to_string()),
+        ]);
+        let input = param(ORDERS, r#"{"by":{"Field":"/city"},"value":"/amount","reduce":"Min","num_type":"Float"}"#);
+        assert_eq!(result(&input)[0], ("bj".to_string(), "1.5".to_string()));
+    }
+
+    #[test]
+    fn error_test() {
+        let is_err = |input: &ConverterParameter| matches!(group_by(input), ConverterReturned::LogicalError { .. });
+        assert!(is_err(&param("abc", r#"{"by":{"Field":"/city"}}"#)));
+        assert!(is_err(&param(r#"[{"a":1}]"#, r#"{"by":{"Field":"/city"}}"#)));
+        assert!(is_err(&param(r#"[{"city":"bj","n":"x"}]"#, r#"{"by":{"Field":"/city"},"value":"/n","reduce":"Sum"}"#)));
+        // `by` is required
+        assert!(is_err(&param(ORDERS, "")));
+        assert!(is_err(&param(ORDERS, r#"{"value":"/amount"}"#)));
+        assert!(is_err(&param(ORDERS, r#"{"by":{"Field":""}}"#)));
+        assert!(is_err(&param(ORDERS, r#"{"by":{"Para":{"field":"","part":[0]}}}"#)));
+    }
+}
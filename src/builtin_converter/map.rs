@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::common::{ConverterParameter, ConverterReturned, get_para_and_key_from_para, Instance, is_default, NatureError, Result, SEPARATOR_INS_PARA};
+
+/// Reshape the upstream `Instance` declaratively.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+struct Setting {
+    /// each one set a field of the downstream content, if empty the upstream content will be used directly.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    content: Vec<Mapping>,
+    /// key is the name of the downstream context
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    context: BTreeMap<String, Source>,
+    /// compose the downstream para, each `Source` will be a part of it.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    para: Vec<Source>,
+    /// copy all the upstream context to downstream before mapping
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    keep_context: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct Mapping {
+    from: Source,
+    /// json pointer of the downstream content, for example "/order/id", the absent objects will be created.
+    to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+enum Source {
+    /// json pointer of the upstream content
+    Field(String),
+    Const(Value),
+    /// key of the upstream context
+    Context(String),
+    /// parts of the upstream para
+    Para(Vec<u8>),
+}
+
+pub fn map(input: &ConverterParameter) -> ConverterReturned {
+    // get setting
+    let cfg = if input.cfg.is_empty() {
+        Setting::default()
+    } else {
+        match serde_json::from_str::<Setting>(&input.cfg) {
+            Ok(cfg) => cfg,
+            Err(err) => {
+                warn!("error setting: {}", &input.cfg);
+                return ConverterReturned::LogicalError { msg: err.to_string() };
+            }
+        }
+    };
+    match map_instance(&cfg, &input.from) {
+        Ok(ins) => ConverterReturned::Instances { ins: vec![ins] },
+        Err(e) => {
+            warn!("builtin-map : {}, instance: {:?}", e, input.from);
+            ConverterReturned::LogicalError { msg: e.to_string() }
+        }
+    }
+}
+
+fn map_instance(cfg: &Setting, from: &Instance) -> Result<Instance> {
+    let mut upstream = Upstream { ins: from, content: None };
+    let mut rtn = Instance::default();
+    // content
+    rtn.content = if cfg.content.is_empty() {
+        from.content.clone()
+    } else {
+        let mut content = Value::Object(Map::new());
+        for one in &cfg.content {
+            let value = upstream.get(&one.from)?;
+            set_by_pointer(&mut content, &one.to, value)?;
+        }
+        content.to_string()
+    };
+    // context
+    if cfg.keep_context {
+        rtn.context = from.context.clone();
+    }
+    for (key, source) in &cfg.context {
+        let value = match upstream.get(source)? {
+            Value::String(s) => s,
+            other => other.to_string()
+        };
+        rtn.context.insert(key.to_string(), value);
+    }
+    // para
+    let mut parts: Vec<String> = Vec::with_capacity(cfg.para.len());
+    for source in &cfg.para {
+        match upstream.get(source)? {
+            Value::String(s) => parts.push(s),
+            Value::Number(n) => parts.push(n.to_string()),
+            Value::Bool(b) => parts.push(b.to_string()),
+            other => return Err(NatureError::VerifyError(format!("can't use {} as para", other)))
+        }
+    }
+    rtn.para = parts.join(SEPARATOR_INS_PARA.as_str());
+    Ok(rtn)
+}
+
+struct Upstream<'a> {
+    ins: &'a Instance,
+    /// parsed upstream content, only parsed when needed
+    content: Option<Value>,
+}
+
+impl Upstream<'_> {
+    fn get(&mut self, source: &Source) -> Result<Value> {
+        match source {
+            Source::Field(pointer) => {
+                if self.content.is_none() {
+                    self.content = Some(serde_json::from_str(&self.ins.content)?);
+                }
+                match self.content.as_ref().unwrap().pointer(pointer) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(NatureError::VerifyError(format!("can't find field {} from upstream content", pointer)))
+                }
+            }
+            Source::Const(v) => Ok(v.clone()),
+            Source::Context(key) => match self.ins.context.get(key) {
+                Some(v) => Ok(Value::String(v.to_string())),
+                None => Err(NatureError::VerifyError(format!("can't find context {} from upstream", key)))
+            },
+            Source::Para(part) => Ok(Value::String(get_para_and_key_from_para(&self.ins.para, part)?.0)),
+        }
+    }
+}
+
+/// set value to the json pointer position, the absent objects will be created.
+fn set_by_pointer(root: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *root = value;
+        return Ok(());
+    }
+    if !pointer.starts_with('/') {
+        return Err(NatureError::VerifyError(format!("json pointer must start with '/': {}", pointer)));
+    }
+    let mut current = root;
+    for token in pointer[1..].split('/') {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+        current = match current {
+            Value::Object(map) => map.entry(token).or_insert(Value::Null),
+            Value::Array(list) => match token.parse::<usize>() {
+                Ok(idx) if idx < list.len() => &mut list[idx],
+                _ => return Err(NatureError::VerifyError(format!("array index error in json pointer: {}", pointer)))
+            },
+            _ => return Err(NatureError::VerifyError(format!("can't set value to json pointer: {}", pointer)))
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn param(cfg: &str) -> ConverterParameter {
+        ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.para = "shop1/2020".to_string();
+                ins.content = r#"{"order":{"id":12,"items":[{"name":"apple"}]},"user":"tom"}"#.to_string();
+                ins.context.insert("channel".to_string(), "web".to_string());
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: cfg.to_string(),
+        }
+    }
+
+    fn instance(input: &ConverterParameter) -> Instance {
+        match map(input) {
+            ConverterReturned::Instances { ins } => ins[0].clone(),
+            _ => panic!("should return instance")
+        }
+    }
+
+    #[test]
+    fn default_test() {
+        let input = param("");
+        let rtn = instance(&input);
+        assert_eq!(rtn.content, input.from.content);
+        assert_eq!(rtn.para, "");
+        assert!(rtn.context.is_empty());
+    }
+
+    #[test]
+    fn content_test() {
+        let cfg = r#"{"content":[
+            {"from":{"Field":"/order/id"},"to":"/id"},
+            {"from":{"Field":"/order/items/0/name"},"to":"/first/name"},
+            {"from":{"Const":"v1"},"to":"/version"},
+            {"from":{"Context":"channel"},"to":"/channel"},
+            {"from":{"Para":[0]},"to":"/shop"}
+        ]}"#;
+        let rtn = instance(&param(cfg));
+        let content: Value = serde_json::from_str(&rtn.content).unwrap();
+        assert_eq!(content, serde_json::json!({"id":12,"first":{"name":"apple"},"version":"v1","channel":"web","shop":"shop1"}));
+    }
+
+    #[test]
+    fn context_and_para_test() {
+        let cfg = r#"{
+            "context":{"user":{"Field":"/user"},"order":{"Field":"/order/id"}},
+            "para":[{"Field":"/user"},{"Para":[1]}],
+            "keep_context":true
+        }"#;
+        let rtn = instance(&param(cfg));
+        assert_eq!(rtn.context.get("user").unwrap(), "tom");
+        assert_eq!(rtn.context.get("order").unwrap(), "12");
+        assert_eq!(rtn.context.get("channel").unwrap(), "web");
+        assert_eq!(rtn.para, "tom/2020");
+    }
+
+    #[test]
+    fn error_test() {
+        let is_err = |cfg: &str| matches!(map(&param(cfg)), ConverterReturned::LogicalError { .. });
+        assert!(is_err(r#"{"content":[{"from":{"Field":"/none"},"to":"/a"}]}"#));
+        assert!(is_err(r#"{"context":{"a":{"Context":"none"}}}"#));
+        assert!(is_err(r#"{"para":[{"Field":"/order"}]}"#));
+        assert!(is_err(r#"{"content":[{"from":{"Const":1},"to":"a"}]}"#));
+    }
+
+    #[test]
+    fn set_by_pointer_test() {
+        let mut root = Value::Null;
+        set_by_pointer(&mut root, "/a~1b/c", Value::from(1)).unwrap();
+        assert_eq!(root, serde_json::json!({"a/b":{"c":1}}));
+        let mut root = serde_json::json!({"a":[1,2]});
+        set_by_pointer(&mut root, "/a/1", Value::from(3)).unwrap();
+        assert_eq!(root, serde_json::json!({"a":[1,3]}));
+        assert!(set_by_pointer(&mut root, "/a/5", Value::from(3)).is_err());
+        set_by_pointer(&mut root, "", Value::from(3)).unwrap();
+        assert_eq!(root, Value::from(3));
+    }
+}
@@ -1,10 +1,16 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::str::FromStr;
 
 use itertools::Itertools;
 
+use aggregate::{Aggregate, Aggregated};
+pub(crate) use num::{Num, NumType};
+
 use crate::common::{ConverterParameter, ConverterReturned, get_para_and_key_from_para, Instance, is_default, NatureError, Result};
 
+mod num;
+mod aggregate;
+
 /// items can't be repeated
 /// detail always save due to recognize the repeated item.
 pub fn merge(input: &ConverterParameter) -> ConverterReturned {
@@ -19,9 +25,12 @@ pub fn merge(input: &ConverterParameter) -> ConverterReturned {
             }
         }
     };
+    if let Err(err) = Aggregate::check(&cfg.aggregates) {
+        return ConverterReturned::LogicalError { msg: err.to_string() };
+    }
     // make input
     let items = match &cfg.key {
-        KeyType::Para(part) => match one_to_vec(&input.from.para, &part, &input.from.content) {
+        KeyType::Para(part) => match one_to_vec(&input.from.para, part, &input.from.content, &cfg.num_type) {
             Ok(rtn) => rtn,
             Err(e) => return ConverterReturned::LogicalError { msg: e.to_string() }
         },
@@ -29,7 +38,9 @@ pub fn merge(input: &ConverterParameter) -> ConverterReturned {
             Ok(items) => {
                 let mut rtn: Vec<Item> = vec![];
                 for item in items {
-                    match serde_json::from_str::<(String, i64)>(&item) {
+                    let value = serde_json::from_str::<(String, Num)>(&item).map_err(NatureError::from)
+                        .and_then(|one| Ok((one.0, one.1.to_type(&cfg.num_type)?)));
+                    match value {
                         Ok(item) => {
                             rtn.push(Item { key: item.0, value: item.1 })
                         }
@@ -51,7 +62,7 @@ pub fn merge(input: &ConverterParameter) -> ConverterReturned {
             Ok(rtn) => {
                 let mut items: Vec<Item> = vec![];
                 for one in rtn {
-                    let value = match i64::from_str(&one) {
+                    let value = match Num::parse(&one, &cfg.num_type) {
                         Ok(num) => num,
                         Err(e) => {
                             let msg = format!("builtin-merge : input format error. {}", e);
@@ -72,8 +83,8 @@ pub fn merge(input: &ConverterParameter) -> ConverterReturned {
     };
     // init result for return
     let mut content = match &input.last_state {
-        None => Content::default(),
-        Some(o_i) => match serde_json::from_str::<Content>(&o_i.content) {
+        None => Content::new(&cfg.num_type),
+        Some(o_i) => match Content::load(&o_i.content, &cfg.num_type) {
             Err(err) => {
                 let msg = format!("builtin-merge : load last error {}. last: {}", err, o_i.content);
                 warn!("{}", msg);
@@ -83,19 +94,29 @@ pub fn merge(input: &ConverterParameter) -> ConverterReturned {
         }
     };
     // summary
-    items.into_iter().for_each(|one| merge_one(&cfg, &mut content, one));
+    for one in items {
+        let key = one.key.to_string();
+        let merged = merge_one(&cfg, &mut content, one).and_then(|kept| match kept {
+            Some(kept) if !cfg.aggregates.is_empty() => content.aggregate.add(&cfg.aggregates, &key, kept),
+            _ => Ok(())
+        });
+        if let Err(e) = merged {
+            return ConverterReturned::LogicalError { msg: format!("builtin-merge : {}", e) };
+        }
+    }
+    content.aggregate.finish(&cfg.aggregates);
     // top it
     top_mode_select(&cfg, &mut content);
 
     // make return instance
     let mut ins = Instance::default();
-    ins.content = if cfg.key == KeyType::None {
+    ins.content = if cfg.key == KeyType::None && cfg.aggregates.is_empty() {
         match content.detail.get("ignore") {
             Some(s) => s.to_string(),
-            None => 0.to_string(),
+            None => Num::zero(&cfg.num_type).to_string(),
         }
     } else {
-        if cfg.sum_all {
+        if cfg.sum_all || !cfg.aggregates.is_empty() {
             match serde_json::to_string(&content) {
                 Ok(s) => s,
                 Err(err) => return ConverterReturned::LogicalError { msg: err.to_string() }
@@ -121,47 +142,56 @@ fn top_mode_select(cfg: &Setting, mut content: &mut Content) {
 }
 
 fn top_it(top: u16, max: bool, content: &mut Content) {
-    let sorted: Vec<(&String, &i64)> = content.detail.iter().sorted_by(|a, b| a.1.cmp(b.1)).collect();
-    let top = top as usize;
-    let top: Vec<(&String, &i64)> = if max {
+    let sorted: Vec<(&String, &Num)> = content.detail.iter()
+        .sorted_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal)).collect();
+    let top = (top as usize).min(sorted.len());
+    let top: Vec<(&String, &Num)> = if max {
         sorted[sorted.len() - top..].to_vec()
     } else {
         sorted[0..top].to_vec()
     };
-    let top: Vec<(String, i64)> = top.iter().map(|one| (one.0.to_string(), *one.1)).collect();
+    let top: Vec<(String, Num)> = top.iter().map(|one| (one.0.to_string(), *one.1)).collect();
     content.detail.clear();
     top.into_iter().for_each(|one| { content.detail.insert(one.0, one.1); });
 }
 
-fn merge_one(cfg: &Setting, content: &mut Content, one: Item) {
+/// return the value should be aggregated: the first one of the key, or the added one for `WhenSame::Sum`
+fn merge_one(cfg: &Setting, content: &mut Content, one: Item) -> Result<Option<Num>> {
+    let zero = Num::zero(&cfg.num_type);
+    let mut kept = None;
     let total_change = match content.detail.insert(one.key.to_string(), one.value) {
-        None => one.value,
+        None => {
+            kept = Some(one.value);
+            one.value
+        }
         Some(old) => match cfg.when_same {
             WhenSame::Old => {
                 content.detail.insert(one.key.to_string(), old);
-                0
+                zero
             }
-            WhenSame::New => one.value - old,
+            WhenSame::New => one.value.checked_sub(old)?,
             WhenSame::Sum => {
-                content.detail.insert(one.key.to_string(), one.value + old);
+                content.detail.insert(one.key.to_string(), one.value.checked_add(old)?);
+                kept = Some(one.value);
                 one.value
             }
             WhenSame::Min => if old < one.value {
                 content.detail.insert(one.key.to_string(), old);
-                0
-            } else { one.value - old }
+                zero
+            } else { one.value.checked_sub(old)? }
             WhenSame::Max => if old > one.value {
                 content.detail.insert(one.key.to_string(), old);
-                0
-            } else { one.value - old }
+                zero
+            } else { one.value.checked_sub(old)? }
         }
     };
     if cfg.sum_all {
-        content.total += total_change;
+        content.total = content.total.checked_add(total_change)?;
     }
+    Ok(kept)
 }
 
-fn one_to_vec(para: &str, idx: &Vec<u8>, value: &str) -> Result<Vec<Item>> {
+fn one_to_vec(para: &str, idx: &Vec<u8>, value: &str, num_type: &NumType) -> Result<Vec<Item>> {
 // prepare parameter
     let (key, _) = match get_para_and_key_from_para(para, idx) {
         Ok(rtn) => rtn,
@@ -171,7 +201,7 @@ fn one_to_vec(para: &str, idx: &Vec<u8>, value: &str) -> Result<Vec<Item>> {
             return Err(NatureError::VerifyError(msg));
         }
     };
-    let num = match i64::from_str(value) {
+    let num = match Num::parse(value, num_type) {
         Err(err) => {
             let msg = format!("builtin-merge : the value be used to sum is not a number. {}", err.to_string());
             warn!("{}, value: {}", msg, value);
@@ -202,6 +232,15 @@ struct Setting {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     top: TopMode,
+    /// the type of the value, default is `Int`
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    num_type: NumType,
+    /// aggregations over the merged items, the result and the intermediate state will be saved to
+    /// the `aggregate` field of the `Content`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    aggregates: Vec<Aggregate>,
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
@@ -233,10 +272,10 @@ impl Default for TopMode {
     }
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 struct Item {
     key: String,
-    value: i64,
+    value: Num,
 }
 
 /// hwo to process the same item's value
@@ -264,10 +303,34 @@ impl Default for WhenSame {
 /// for example: item1,2,100  // the custom bought 2 item1 and paid $100.
 #[derive(Deserialize, Serialize, Default, Debug)]
 struct Content {
-    detail: HashMap<String, i64>,
-    #[serde(skip_serializing_if = "is_default")]
+    detail: HashMap<String, Num>,
+    #[serde(skip_serializing_if = "Num::is_zero")]
+    #[serde(default)]
+    total: Num,
+    #[serde(skip_serializing_if = "Aggregated::is_empty")]
     #[serde(default)]
-    total: i64,
+    aggregate: Aggregated,
+}
+
+impl Content {
+    fn new(num_type: &NumType) -> Self {
+        Content {
+            detail: HashMap::new(),
+            total: Num::zero(num_type),
+            aggregate: Aggregated::default(),
+        }
+    }
+
+    /// load from the last state, and convert the values to `num_type`
+    fn load(json: &str, num_type: &NumType) -> Result<Self> {
+        let mut rtn = serde_json::from_str::<Content>(json)?;
+        for v in rtn.detail.values_mut() {
+            *v = v.to_type(num_type)?;
+        }
+        rtn.total = rtn.total.to_type(num_type)?;
+        rtn.aggregate.convert_type(num_type)?;
+        Ok(rtn)
+    }
 }
 
 #[cfg(test)]
@@ -278,36 +341,38 @@ mod top_test {
     fn top_max_text() {
         let mut input = Content {
             detail: HashMap::default(),
-            total: 500,
+            total: Num::Int(500),
+            aggregate: Default::default(),
         };
-        input.detail.insert("a".to_string(), 100);
-        input.detail.insert("b".to_string(), 700);
-        input.detail.insert("c".to_string(), 10);
-        input.detail.insert("d".to_string(), 50);
-        input.detail.insert("e".to_string(), 200);
+        input.detail.insert("a".to_string(), Num::Int(100));
+        input.detail.insert("b".to_string(), Num::Int(700));
+        input.detail.insert("c".to_string(), Num::Int(10));
+        input.detail.insert("d".to_string(), Num::Int(50));
+        input.detail.insert("e".to_string(), Num::Int(200));
         top_it(3, true, &mut input);
         assert_eq!(input.detail.len(), 3);
-        assert_eq!(input.detail.get("b"), Some(&700));
-        assert_eq!(input.detail.get("e"), Some(&200));
-        assert_eq!(input.detail.get("a"), Some(&100));
+        assert_eq!(input.detail.get("b"), Some(&Num::Int(700)));
+        assert_eq!(input.detail.get("e"), Some(&Num::Int(200)));
+        assert_eq!(input.detail.get("a"), Some(&Num::Int(100)));
     }
 
     #[test]
     fn top_min_text() {
         let mut input = Content {
             detail: HashMap::default(),
-            total: 500,
+            total: Num::Int(500),
+            aggregate: Default::default(),
         };
-        input.detail.insert("a".to_string(), 100);
-        input.detail.insert("b".to_string(), 700);
-        input.detail.insert("c".to_string(), 10);
-        input.detail.insert("d".to_string(), 50);
-        input.detail.insert("e".to_string(), 200);
+        input.detail.insert("a".to_string(), Num::Int(100));
+        input.detail.insert("b".to_string(), Num::Int(700));
+        input.detail.insert("c".to_string(), Num::Int(10));
+        input.detail.insert("d".to_string(), Num::Int(50));
+        input.detail.insert("e".to_string(), Num::Int(200));
         top_it(3, false, &mut input);
         assert_eq!(input.detail.len(), 3);
-        assert_eq!(input.detail.get("c"), Some(&10));
-        assert_eq!(input.detail.get("d"), Some(&50));
-        assert_eq!(input.detail.get("a"), Some(&100));
+        assert_eq!(input.detail.get("c"), Some(&Num::Int(10)));
+        assert_eq!(input.detail.get("d"), Some(&Num::Int(50)));
+        assert_eq!(input.detail.get("a"), Some(&Num::Int(100)));
     }
 }
 
@@ -338,6 +403,8 @@ mod para_type_test {
                 when_same: Default::default(),
                 sum_all: false,
                 top: Default::default(),
+                num_type: Default::default(),
+                aggregates: vec![],
             }).unwrap(),
         };
         dbg!(&input.cfg);
@@ -364,6 +431,8 @@ mod para_type_test {
                 when_same: Default::default(),
                 sum_all: false,
                 top: Default::default(),
+                num_type: Default::default(),
+                aggregates: vec![],
             }).unwrap(),
         };
         if let ConverterReturned::LogicalError { msg: e } = merge(&input) {
@@ -390,6 +459,8 @@ mod para_type_test {
                 when_same: Default::default(),
                 sum_all: true,
                 top: Default::default(),
+                num_type: Default::default(),
+                aggregates: vec![],
             }).unwrap(),
         };
         match merge(&input) {
@@ -424,6 +495,8 @@ mod para_type_test {
             when_same: Default::default(),  // sum
             sum_all: true,
             top: Default::default(),
+            num_type: Default::default(),
+            aggregates: vec![],
         }).unwrap();
         match merge(&input) {
             ConverterReturned::Instances { ins: rtn } => {
@@ -437,6 +510,8 @@ mod para_type_test {
             when_same: WhenSame::Old,
             sum_all: true,
             top: Default::default(),
+            num_type: Default::default(),
+            aggregates: vec![],
         }).unwrap();
         dbg!(&input.cfg);
         match merge(&input) {
@@ -451,6 +526,8 @@ mod para_type_test {
             when_same: WhenSame::New,
             sum_all: true,
             top: Default::default(),
+            num_type: Default::default(),
+            aggregates: vec![],
         }).unwrap();
         match merge(&input) {
             ConverterReturned::Instances { ins: rtn } => {
@@ -464,6 +541,8 @@ mod para_type_test {
             when_same: WhenSame::Max,
             sum_all: true,
             top: Default::default(),
+            num_type: Default::default(),
+            aggregates: vec![],
         }).unwrap();
         match merge(&input) {
             ConverterReturned::Instances { ins: rtn } => {
@@ -477,6 +556,8 @@ mod para_type_test {
             when_same: WhenSame::Min,
             sum_all: true,
             top: Default::default(),
+            num_type: Default::default(),
+            aggregates: vec![],
         }).unwrap();
         match merge(&input) {
             ConverterReturned::Instances { ins: rtn } => {
@@ -710,6 +791,236 @@ mod content_none_key_test {
     }
 }
 
+#[cfg(test)]
+mod num_type_test {
+    use super::*;
+
+    #[test]
+    fn decimal() {
+        let input = ConverterParameter {
+            from: {
+                let mut rtn = Instance::default();
+                rtn.para = "a/b/c".to_string();
+                rtn.content = "0.66".to_string();
+                rtn
+            },
+            last_state: Some({
+                let mut rtn = Instance::default();
+                rtn.content = r#"{"detail":{"b":"12.34","c":5},"total":"17.34"}"#.to_string();
+                rtn
+            }),
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":{"Para":[1]},"sum_all":true,"num_type":{"Decimal":2}}"#.to_string(),
+        };
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                let content: serde_json::Value = serde_json::from_str(&rtn[0].content).unwrap();
+                assert_eq!(content["detail"]["b"], "13.00");
+                assert_eq!(content["detail"]["c"], "5.00");
+                assert_eq!(content["total"], "18.00");
+            }
+            _ => panic!("error")
+        }
+    }
+
+    #[test]
+    fn decimal_too_many_places() {
+        let input = ConverterParameter {
+            from: {
+                let mut rtn = Instance::default();
+                rtn.para = "a/b/c".to_string();
+                rtn.content = "0.666".to_string();
+                rtn
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":{"Para":[1]},"num_type":{"Decimal":2}}"#.to_string(),
+        };
+        if let ConverterReturned::LogicalError { msg: e } = merge(&input) {
+            assert!(e.contains("is not a number"));
+        } else {
+            panic!("should return error");
+        }
+    }
+
+    #[test]
+    fn old_integer_state() {
+        let input = ConverterParameter {
+            from: {
+                let mut rtn = Instance::default();
+                rtn.para = "a/b/c".to_string();
+                rtn.content = "1.5".to_string();
+                rtn
+            },
+            last_state: Some({
+                let mut rtn = Instance::default();
+                rtn.content = r#"{"detail":{"b":2},"total":2}"#.to_string();
+                rtn
+            }),
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":{"Para":[1]},"sum_all":true,"num_type":"Float"}"#.to_string(),
+        };
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                assert_eq!(rtn[0].content, r#"{"detail":{"b":3.5},"total":3.5}"#);
+            }
+            _ => panic!("error")
+        }
+    }
+
+    #[test]
+    fn float_top() {
+        let data: Vec<String> = vec![
+            r#"["a", 1.5]"#.to_string(),
+            r#"["b", 0.5]"#.to_string(),
+            r#"["c", 2]"#.to_string(),
+        ];
+        let input = ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.content = serde_json::to_string(&data).unwrap();
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":"Content","top":{"MaxTop":2},"num_type":"Float"}"#.to_string(),
+        };
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                let content: HashMap<String, f64> = serde_json::from_str(&rtn[0].content).unwrap();
+                assert_eq!(content.len(), 2);
+                assert_eq!(content.get("a"), Some(&1.5));
+                assert_eq!(content.get("c"), Some(&2.0));
+            }
+            _ => panic!("error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod aggregate_test {
+    use super::*;
+
+    #[test]
+    fn avg_with_last() {
+        let mut input = ConverterParameter {
+            from: {
+                let mut rtn = Instance::default();
+                rtn.para = "a/b/c".to_string();
+                rtn.content = "10".to_string();
+                rtn
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":{"Para":[1]},"aggregates":["Count","Avg","Max"]}"#.to_string(),
+        };
+        let last = match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => rtn[0].clone(),
+            _ => panic!("error")
+        };
+        assert_eq!(last.content, r#"{"detail":{"b":10},"aggregate":{"count":1,"sum":10,"avg":10.0,"max":10}}"#);
+        input.from.para = "a/c/c".to_string();
+        input.from.content = "20".to_string();
+        input.last_state = Some(last);
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                let content: serde_json::Value = serde_json::from_str(&rtn[0].content).unwrap();
+                let expected: serde_json::Value = serde_json::from_str(r#"{"detail":{"b":10,"c":20},"aggregate":{"count":2,"sum":30,"avg":15.0,"max":20}}"#).unwrap();
+                assert_eq!(content, expected);
+            }
+            _ => panic!("error")
+        }
+    }
+
+    #[test]
+    fn same_key_old() {
+        let mut input = ConverterParameter {
+            from: {
+                let mut rtn = Instance::default();
+                rtn.para = "a/b/c".to_string();
+                rtn.content = "10".to_string();
+                rtn
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"key":{"Para":[1]},"when_same":"Old","aggregates":["Count","Avg","Max"]}"#.to_string(),
+        };
+        let last = match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => rtn[0].clone(),
+            _ => panic!("error")
+        };
+        input.from.content = "20".to_string();
+        input.last_state = Some(last);
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                assert_eq!(rtn[0].content, r#"{"detail":{"b":10},"aggregate":{"count":1,"sum":10,"avg":10.0,"max":10}}"#);
+            }
+            _ => panic!("error")
+        }
+        // the added value is aggregated for `Sum`
+        input.cfg = r#"{"key":{"Para":[1]},"aggregates":["Count","Avg","Max"]}"#.to_string();
+        input.last_state = None;
+        let last = match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => rtn[0].clone(),
+            _ => panic!("error")
+        };
+        input.last_state = Some(last);
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                assert_eq!(rtn[0].content, r#"{"detail":{"b":40},"aggregate":{"count":2,"sum":40,"avg":20.0,"max":20}}"#);
+            }
+            _ => panic!("error")
+        }
+    }
+
+    #[test]
+    fn none_key() {
+        let input = ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.content = r#"["1","2","3"]"#.to_string();
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"aggregates":["Min",{"Percentile":[50]}]}"#.to_string(),
+        };
+        match merge(&input) {
+            ConverterReturned::Instances { ins: rtn } => {
+                let content: serde_json::Value = serde_json::from_str(&rtn[0].content).unwrap();
+                assert_eq!(content["detail"]["ignore"], 6);
+                assert_eq!(content["aggregate"]["min"], 1);
+                let p50 = content["aggregate"]["percentile"]["50"].as_f64().unwrap();
+                assert!((p50 - 2.0).abs() < 0.05);
+            }
+            _ => panic!("error")
+        }
+    }
+
+    #[test]
+    fn wrong_percentile() {
+        let input = ConverterParameter {
+            from: Default::default(),
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"aggregates":[{"Percentile":[120]}]}"#.to_string(),
+        };
+        if let ConverterReturned::LogicalError { msg: e } = merge(&input) {
+            assert!(e.contains("percentile"));
+        } else {
+            panic!("should return error");
+        }
+    }
+}
+
 #[cfg(test)]
 mod config_test {
     use super::*;
@@ -722,6 +1033,8 @@ mod config_test {
             when_same: Default::default(),
             sum_all: false,
             top: TopMode::MaxTop(2),
+            num_type: Default::default(),
+            aggregates: vec![],
         };
         let string = serde_json::to_string(&setting).unwrap();
         dbg!(string);
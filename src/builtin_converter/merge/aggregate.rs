@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::common::{is_default, NatureError, Result};
+
+use super::num::{Num, NumType};
+
+/// Precision of the `DistinctCount`, 2^10 registers, the standard error is about 3.25%
+const HLL_PRECISION: u8 = 10;
+/// Relative accuracy of the `Percentile`
+const SKETCH_ACCURACY: f64 = 0.01;
+
+/// Aggregations applied to every item merged into the state instance.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+pub enum Aggregate {
+    /// how many items have been merged
+    Count,
+    /// keeps `sum` and `count` and outputs `avg`
+    Avg,
+    /// the minimum value of the merged items
+    Min,
+    /// the maximum value of the merged items
+    Max,
+    /// approximate distinct count of the item keys, HyperLogLog
+    DistinctCount,
+    /// approximate percentiles of the item values, for example `Percentile([50, 90, 99])`
+    Percentile(Vec<u8>),
+}
+
+impl Aggregate {
+    pub fn check(list: &[Aggregate]) -> Result<()> {
+        for one in list {
+            if let Aggregate::Percentile(ps) = one {
+                if ps.is_empty() || ps.iter().any(|p| *p > 100) {
+                    return Err(NatureError::VerifyError(format!("percentile should be in [0, 100], but get {:?}", ps)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The intermediate state and the result of the aggregations, saved in `Content`.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Aggregated {
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    sum: Option<Num>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    avg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    min: Option<Num>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    max: Option<Num>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    distinct: Option<Hll>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    distinct_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    sketch: Option<Sketch>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    percentile: BTreeMap<u8, f64>,
+}
+
+impl Aggregated {
+    /// This is only used for serialize
+    pub fn is_empty(&self) -> bool {
+        *self == Aggregated::default()
+    }
+
+    /// convert the loaded values to `num_type`
+    pub fn convert_type(&mut self, num_type: &NumType) -> Result<()> {
+        for v in self.sum.iter_mut().chain(self.min.iter_mut()).chain(self.max.iter_mut()) {
+            *v = v.to_type(num_type)?;
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, aggregates: &[Aggregate], key: &str, value: Num) -> Result<()> {
+        let mut counted = false;
+        for one in aggregates {
+            match one {
+                Aggregate::Count => counted = true,
+                Aggregate::Avg => {
+                    counted = true;
+                    self.sum = Some(match self.sum {
+                        None => value,
+                        Some(sum) => sum.checked_add(value)?
+                    })
+                }
+                Aggregate::Min => self.min = match self.min {
+                    Some(min) if min <= value => Some(min),
+                    _ => Some(value)
+                },
+                Aggregate::Max => self.max = match self.max {
+                    Some(max) if max >= value => Some(max),
+                    _ => Some(value)
+                },
+                Aggregate::DistinctCount => self.distinct.get_or_insert_with(Hll::new).add(key),
+                Aggregate::Percentile(_) => self.sketch.get_or_insert_with(Sketch::default).add(value.as_f64()),
+            }
+        }
+        // `count` is output only when it is asked for
+        if counted {
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    /// generate the outputs from the intermediate state
+    pub fn finish(&mut self, aggregates: &[Aggregate]) {
+        for one in aggregates {
+            match one {
+                Aggregate::Avg => if let Some(sum) = self.sum {
+                    self.avg = Some(sum.as_f64() / self.count as f64);
+                },
+                Aggregate::DistinctCount => if let Some(hll) = &self.distinct {
+                    self.distinct_count = Some(hll.count());
+                },
+                Aggregate::Percentile(ps) => if let Some(sketch) = &self.sketch {
+                    for p in ps {
+                        if let Some(v) = sketch.quantile(*p as f64 / 100.0) {
+                            self.percentile.insert(*p, v);
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+    }
+}
+
+/// HyperLogLog registers, saved as hex string.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Hll {
+    #[serde(serialize_with = "registers_to_hex")]
+    #[serde(deserialize_with = "registers_from_hex")]
+    reg: Vec<u8>,
+}
+
+impl Hll {
+    fn new() -> Self {
+        Hll { reg: vec![0; 1 << HLL_PRECISION] }
+    }
+
+    fn add(&mut self, key: &str) {
+        let hash = hash64(key.as_bytes());
+        let idx = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION).leading_zeros() as u8 + 1).min(64 - HLL_PRECISION + 1);
+        if self.reg.len() != 1 << HLL_PRECISION {
+            self.reg.resize(1 << HLL_PRECISION, 0);
+        }
+        if self.reg[idx] < rank {
+            self.reg[idx] = rank;
+        }
+    }
+
+    fn count(&self) -> u64 {
+        let m = self.reg.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.reg.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.reg.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// FNV-1a with a 64-bit finalizer, it must be stable because the registers are persisted.
+fn hash64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in data {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+fn registers_to_hex<S>(reg: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+    let hex: String = reg.iter().map(|r| format!("{:02x}", r)).collect();
+    serializer.serialize_str(&hex)
+}
+
+fn registers_from_hex<'de, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> where D: Deserializer<'de> {
+    let hex = String::deserialize(deserializer)?;
+    if hex.len() % 2 != 0 {
+        return Err(serde::de::Error::custom("registers length error"));
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Log-bucketed histogram for percentiles, the error of the result relative to the real value
+/// is less than `SKETCH_ACCURACY`.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Sketch {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    pos: BTreeMap<i32, u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    neg: BTreeMap<i32, u64>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    zero: u64,
+}
+
+impl Sketch {
+    fn gamma() -> f64 {
+        (1.0 + SKETCH_ACCURACY) / (1.0 - SKETCH_ACCURACY)
+    }
+
+    fn add(&mut self, value: f64) {
+        if value == 0.0 {
+            self.zero += 1;
+            return;
+        }
+        let idx = (value.abs().ln() / Self::gamma().ln()).ceil() as i32;
+        let buckets = if value > 0.0 { &mut self.pos } else { &mut self.neg };
+        *buckets.entry(idx).or_insert(0) += 1;
+    }
+
+    fn value_of(idx: i32) -> f64 {
+        let gamma = Self::gamma();
+        2.0 * gamma.powi(idx) / (gamma + 1.0)
+    }
+
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let total: u64 = self.pos.values().sum::<u64>() + self.neg.values().sum::<u64>() + self.zero;
+        if total == 0 {
+            return None;
+        }
+        let rank = (q * (total - 1) as f64).round() as u64;
+        let mut passed = 0u64;
+        for (idx, cnt) in self.neg.iter().rev() {
+            passed += cnt;
+            if passed > rank {
+                return Some(-Self::value_of(*idx));
+            }
+        }
+        passed += self.zero;
+        if passed > rank {
+            return Some(0.0);
+        }
+        for (idx, cnt) in self.pos.iter() {
+            passed += cnt;
+            if passed > rank {
+                return Some(Self::value_of(*idx));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_test() {
+        assert!(Aggregate::check(&[Aggregate::Percentile(vec![50, 99])]).is_ok());
+        assert!(Aggregate::check(&[Aggregate::Percentile(vec![101])]).is_err());
+        assert!(Aggregate::check(&[Aggregate::Percentile(vec![])]).is_err());
+    }
+
+    #[test]
+    fn count_avg_min_max_test() {
+        let aggs = vec![Aggregate::Count, Aggregate::Avg, Aggregate::Min, Aggregate::Max];
+        let mut agg = Aggregated::default();
+        agg.add(&aggs, "a", Num::Int(3)).unwrap();
+        agg.add(&aggs, "b", Num::Int(1)).unwrap();
+        agg.add(&aggs, "a", Num::Int(8)).unwrap();
+        agg.finish(&aggs);
+        assert_eq!(agg.count, 3);
+        assert_eq!(agg.sum, Some(Num::Int(12)));
+        assert_eq!(agg.avg, Some(4.0));
+        assert_eq!(agg.min, Some(Num::Int(1)));
+        assert_eq!(agg.max, Some(Num::Int(8)));
+    }
+
+    #[test]
+    fn distinct_test() {
+        let aggs = vec![Aggregate::DistinctCount];
+        let mut agg = Aggregated::default();
+        for i in 0..10000 {
+            agg.add(&aggs, &format!("key{}", i % 5000), Num::Int(1)).unwrap();
+        }
+        agg.finish(&aggs);
+        let count = agg.distinct_count.unwrap() as f64;
+        assert!((count - 5000.0).abs() / 5000.0 < 0.1);
+
+        // small set is exact enough
+        let mut agg = Aggregated::default();
+        agg.add(&aggs, "a", Num::Int(1)).unwrap();
+        agg.add(&aggs, "b", Num::Int(1)).unwrap();
+        agg.add(&aggs, "a", Num::Int(1)).unwrap();
+        agg.finish(&aggs);
+        assert_eq!(agg.distinct_count, Some(2));
+        assert_eq!(agg.count, 0);
+        assert!(!serde_json::to_string(&agg).unwrap().contains("\"count\""));
+    }
+
+    #[test]
+    fn overflow_test() {
+        let aggs = vec![Aggregate::Avg];
+        let mut agg = Aggregated::default();
+        agg.add(&aggs, "a", Num::Int(i64::MAX)).unwrap();
+        assert!(agg.add(&aggs, "b", Num::Int(1)).is_err());
+    }
+
+    #[test]
+    fn percentile_test() {
+        let aggs = vec![Aggregate::Percentile(vec![0, 50, 90, 100])];
+        let mut agg = Aggregated::default();
+        for i in 1..=1000 {
+            agg.add(&aggs, "a", Num::Int(i)).unwrap();
+        }
+        agg.finish(&aggs);
+        let check = |p: u8, expect: f64| {
+            let v = agg.percentile.get(&p).unwrap();
+            assert!((v - expect).abs() / expect <= 2.0 * SKETCH_ACCURACY, "p{} = {}", p, v);
+        };
+        check(0, 1.0);
+        check(50, 500.0);
+        check(90, 900.0);
+        check(100, 1000.0);
+    }
+
+    #[test]
+    fn percentile_negative_test() {
+        let mut sketch = Sketch::default();
+        vec![-10.0, 0.0, 10.0].into_iter().for_each(|v| sketch.add(v));
+        assert!((sketch.quantile(0.0).unwrap() + 10.0).abs() < 0.1);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert!((sketch.quantile(1.0).unwrap() - 10.0).abs() < 0.1);
+        assert_eq!(Sketch::default().quantile(0.5), None);
+    }
+
+    #[test]
+    fn serde_test() {
+        let aggs = vec![Aggregate::Avg, Aggregate::DistinctCount, Aggregate::Percentile(vec![50])];
+        let mut agg = Aggregated::default();
+        agg.add(&aggs, "a", Num::Decimal(150, 2)).unwrap();
+        agg.add(&aggs, "b", Num::Decimal(250, 2)).unwrap();
+        agg.finish(&aggs);
+        let json = serde_json::to_string(&agg).unwrap();
+        assert!(json.contains(r#""sum":"4.00""#));
+        let mut loaded: Aggregated = serde_json::from_str(&json).unwrap();
+        loaded.convert_type(&NumType::Decimal(2)).unwrap();
+        assert_eq!(loaded, agg);
+    }
+}
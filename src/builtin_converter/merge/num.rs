@@ -0,0 +1,288 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{Error, Visitor};
+
+use crate::common::{NatureError, Result};
+
+/// which kind of number the `merge` will process
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub enum NumType {
+    #[default]
+    Int,
+    Float,
+    /// exact decimal with fixed scale, for example `Decimal(2)` for amounts with cents.
+    /// It will be saved as string to avoid losing precision.
+    Decimal(u8),
+}
+
+/// The value used by `merge`, `Decimal` holds the value of the minimal unit and the scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Num {
+    Int(i64),
+    Float(f64),
+    Decimal(i64, u8),
+}
+
+impl Default for Num {
+    fn default() -> Self {
+        Num::Int(0)
+    }
+}
+
+impl Num {
+    pub fn zero(num_type: &NumType) -> Self {
+        match num_type {
+            NumType::Int => Num::Int(0),
+            NumType::Float => Num::Float(0.0),
+            NumType::Decimal(scale) => Num::Decimal(0, *scale),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Num::Int(v) => *v == 0,
+            Num::Float(v) => *v == 0.0,
+            Num::Decimal(v, _) => *v == 0,
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Num::Int(v) => *v as f64,
+            Num::Float(v) => *v,
+            Num::Decimal(v, s) => *v as f64 / 10f64.powi(*s as i32),
+        }
+    }
+
+    /// convert to the wanted type, integer can't be lost precision.
+    pub fn to_type(self, num_type: &NumType) -> Result<Num> {
+        match (num_type, self) {
+            (NumType::Int, Num::Int(_)) => Ok(self),
+            (NumType::Int, Num::Float(v)) => {
+                if v.fract() == 0.0 {
+                    Ok(Num::Int(v as i64))
+                } else {
+                    Err(NatureError::VerifyError(format!("{} is not an integer", self)))
+                }
+            }
+            (NumType::Int, Num::Decimal(v, s)) => {
+                let p = pow10(s)?;
+                if v % p == 0 {
+                    Ok(Num::Int(v / p))
+                } else {
+                    Err(NatureError::VerifyError(format!("{} is not an integer", self)))
+                }
+            }
+            (NumType::Float, _) => Ok(Num::Float(self.as_f64())),
+            (NumType::Decimal(scale), Num::Int(v)) => Ok(Num::Decimal(checked_mul(v, pow10(*scale)?)?, *scale)),
+            (NumType::Decimal(scale), Num::Float(v)) => Ok(Num::Decimal((v * pow10(*scale)? as f64).round() as i64, *scale)),
+            (NumType::Decimal(scale), Num::Decimal(v, s)) => {
+                if s <= *scale {
+                    return Ok(Num::Decimal(checked_mul(v, pow10(*scale - s)?)?, *scale));
+                }
+                let p = pow10(s - *scale)?;
+                if v % p == 0 {
+                    Ok(Num::Decimal(v / p, *scale))
+                } else {
+                    Err(NatureError::VerifyError(format!("{} has more decimal places than {}", self, scale)))
+                }
+            }
+        }
+    }
+
+    /// parse and convert to the wanted type
+    pub fn parse(s: &str, num_type: &NumType) -> Result<Num> {
+        Num::from_str(s)?.to_type(num_type)
+    }
+}
+
+fn pow10(scale: u8) -> Result<i64> {
+    match 10i64.checked_pow(scale as u32) {
+        Some(p) => Ok(p),
+        None => Err(NatureError::VerifyError(format!("decimal scale is too big: {}", scale)))
+    }
+}
+
+fn checked_mul(v: i64, p: i64) -> Result<i64> {
+    match v.checked_mul(p) {
+        Some(rtn) => Ok(rtn),
+        None => Err(NatureError::VerifyError(format!("number overflow: {} * {}", v, p)))
+    }
+}
+
+impl FromStr for Num {
+    type Err = NatureError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Ok(v) = i64::from_str(s) {
+            return Ok(Num::Int(v));
+        }
+        let parts: Vec<&str> = s.splitn(2, '.').collect();
+        if parts.len() == 2 && !parts[1].is_empty() && parts[1].chars().all(|c| c.is_ascii_digit()) {
+            let scale = parts[1].len() as u8;
+            if let Ok(v) = i64::from_str(&format!("{}{}", parts[0], parts[1])) {
+                if !parts[0].trim_start_matches('-').is_empty() {
+                    return Ok(Num::Decimal(v, scale));
+                }
+            }
+        }
+        match f64::from_str(s) {
+            Ok(v) if v.is_finite() => Ok(Num::Float(v)),
+            _ => Err(NatureError::VerifyError(format!("[{}] is not a number", s)))
+        }
+    }
+}
+
+impl Display for Num {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Num::Int(v) => write!(f, "{}", v),
+            Num::Float(v) => write!(f, "{}", v),
+            Num::Decimal(v, s) => {
+                let sign = if *v < 0 { "-" } else { "" };
+                let abs = (*v as i128).abs();
+                let p = 10i128.pow(*s as u32);
+                match s {
+                    0 => write!(f, "{}{}", sign, abs),
+                    _ => write!(f, "{}{}.{:0width$}", sign, abs / p, abs % p, width = *s as usize)
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Num {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Num::Int(a), Num::Int(b)) => a.partial_cmp(b),
+            (Num::Decimal(a, sa), Num::Decimal(b, sb)) if sa == sb => a.partial_cmp(b),
+            _ => self.as_f64().partial_cmp(&other.as_f64())
+        }
+    }
+}
+
+impl Num {
+    pub fn checked_add(self, other: Num) -> Result<Num> {
+        let rtn = match (self, other) {
+            (Num::Int(a), Num::Int(b)) => a.checked_add(b).map(Num::Int),
+            (Num::Decimal(a, sa), Num::Decimal(b, sb)) if sa == sb => a.checked_add(b).map(|v| Num::Decimal(v, sa)),
+            _ => Some(Num::Float(self.as_f64() + other.as_f64()))
+        };
+        rtn.ok_or_else(|| NatureError::LogicalError(format!("number overflow: {} + {}", self, other)))
+    }
+
+    pub fn checked_sub(self, other: Num) -> Result<Num> {
+        let rtn = match (self, other) {
+            (Num::Int(a), Num::Int(b)) => a.checked_sub(b).map(Num::Int),
+            (Num::Decimal(a, sa), Num::Decimal(b, sb)) if sa == sb => a.checked_sub(b).map(|v| Num::Decimal(v, sa)),
+            _ => Some(Num::Float(self.as_f64() - other.as_f64()))
+        };
+        rtn.ok_or_else(|| NatureError::LogicalError(format!("number overflow: {} - {}", self, other)))
+    }
+}
+
+impl Serialize for Num {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        match self {
+            Num::Int(v) => serializer.serialize_i64(*v),
+            Num::Float(v) => serializer.serialize_f64(*v),
+            Num::Decimal(_, _) => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+/// accept json number or string, the result should be converted by `Num::to_type` before using.
+impl<'de> Deserialize<'de> for Num {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_any(NumVisitor)
+    }
+}
+
+struct NumVisitor;
+
+impl<'de> Visitor<'de> for NumVisitor {
+    type Value = Num;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a number or a string of number")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Num, E> where E: Error {
+        Ok(Num::Int(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> std::result::Result<Num, E> where E: Error {
+        if v > i64::MAX as u64 {
+            return Ok(Num::Float(v as f64));
+        }
+        Ok(Num::Int(v as i64))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Num, E> where E: Error {
+        Ok(Num::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Num, E> where E: Error {
+        Num::from_str(v).map_err(|e| E::custom(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!(Num::from_str("12").unwrap(), Num::Int(12));
+        assert_eq!(Num::from_str("-12.05").unwrap(), Num::Decimal(-1205, 2));
+        assert_eq!(Num::from_str("1e3").unwrap(), Num::Float(1000.0));
+        assert_eq!(Num::from_str(".5").unwrap(), Num::Float(0.5));
+        assert!(Num::from_str("").is_err());
+        assert!(Num::from_str("abc").is_err());
+    }
+
+    #[test]
+    fn to_type_test() {
+        let d2 = NumType::Decimal(2);
+        assert_eq!(Num::Int(12).to_type(&d2).unwrap(), Num::Decimal(1200, 2));
+        assert_eq!(Num::Decimal(15, 1).to_type(&d2).unwrap(), Num::Decimal(150, 2));
+        assert_eq!(Num::Decimal(1500, 3).to_type(&d2).unwrap(), Num::Decimal(150, 2));
+        assert!(Num::Decimal(1505, 3).to_type(&d2).is_err());
+        assert_eq!(Num::Float(0.1).to_type(&d2).unwrap(), Num::Decimal(10, 2));
+        assert_eq!(Num::Decimal(150, 2).to_type(&NumType::Float).unwrap(), Num::Float(1.5));
+        assert_eq!(Num::Decimal(1500, 2).to_type(&NumType::Int).unwrap(), Num::Int(15));
+        assert!(Num::Float(1.5).to_type(&NumType::Int).is_err());
+    }
+
+    #[test]
+    fn display_test() {
+        assert_eq!(Num::Decimal(1205, 2).to_string(), "12.05");
+        assert_eq!(Num::Decimal(-5, 2).to_string(), "-0.05");
+        assert_eq!(Num::Decimal(5, 0).to_string(), "5");
+        assert_eq!(Num::Float(1.5).to_string(), "1.5");
+    }
+
+    #[test]
+    fn calculate_test() {
+        assert_eq!(Num::Decimal(10, 2).checked_add(Num::Decimal(20, 2)).unwrap(), Num::Decimal(30, 2));
+        assert_eq!(Num::Decimal(10, 2).checked_sub(Num::Decimal(20, 2)).unwrap(), Num::Decimal(-10, 2));
+        assert_eq!(Num::Int(1).checked_add(Num::Int(2)).unwrap(), Num::Int(3));
+        assert_eq!(Num::Float(0.5).checked_add(Num::Float(0.25)).unwrap(), Num::Float(0.75));
+        assert!(Num::Int(i64::MAX).checked_add(Num::Int(1)).is_err());
+        assert!(Num::Decimal(i64::MIN, 2).checked_sub(Num::Decimal(1, 2)).is_err());
+        assert!(Num::Decimal(10, 2) < Num::Decimal(20, 2));
+    }
+
+    #[test]
+    fn serde_test() {
+        let rtn: Vec<Num> = serde_json::from_str(r#"[1, 1.5, "2.25"]"#).unwrap();
+        assert_eq!(rtn, vec![Num::Int(1), Num::Float(1.5), Num::Decimal(225, 2)]);
+        let json = serde_json::to_string(&rtn).unwrap();
+        assert_eq!(json, r#"[1,1.5,"2.25"]"#);
+    }
+}
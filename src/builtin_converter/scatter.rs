@@ -1,7 +1,8 @@
 use serde::Deserialize;
+use serde_json::Value;
 use serde_json::value::RawValue;
 
-use crate::common::{ConverterParameter, ConverterReturned, default_para_separator, Instance, is_default_para_separator, SEPARATOR_INS_PARA};
+use crate::common::{ConverterParameter, ConverterReturned, default_para_separator, Instance, is_default_para_separator, NatureError, Result, SEPARATOR_INS_PARA};
 
 /// Setting is a json, include the following properties:
 /// each you defined dimensions will be output as `Instance.para`
@@ -11,6 +12,25 @@ struct Setting {
     #[serde(skip_serializing_if = "is_default_para_separator")]
     #[serde(default = "default_para_separator")]
     pub dimension_separator: String,
+    /// scatter natural json instead of `Vec<Item>`, see `Nested`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub nested: Option<Nested>,
+}
+
+/// Flatten the nested json by paths. A path is a json pointer, and "*" can be used to
+/// iterate all the elements of an array or all the members of an object, for example:
+/// `{"class":"c5","students":[{"name":"n1","scores":{"math":92}}]}` with dimensions
+/// `["/class", "/students/*/name", "/students/*/scores/*"]` and value `"/students/*/scores/*"`
+/// will output an `Instance` with para "c5/n1/math" and content 92.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Nested {
+    /// each one will be a part of the `Instance.para`. The "*" in it will take the same element as
+    /// the "*" at the same position of `value`, so the parts before it must be the same as `value`'s.
+    /// If the path ends with "*", the member name or the array index will be used.
+    dimensions: Vec<String>,
+    /// each value found by this path will be a downstream `Instance.content`
+    value: String,
 }
 
 /// each item in the table which will be scattered
@@ -41,6 +61,12 @@ pub fn scatter(para: &ConverterParameter) -> ConverterReturned {
         return ConverterReturned::LogicalError { msg: msg };
     }
     let set = set.unwrap();
+    if let Some(nested) = &set.nested {
+        return match scatter_nested(nested, &para.from.content) {
+            Ok(ins) => ConverterReturned::Instances { ins },
+            Err(e) => ConverterReturned::LogicalError { msg: e.to_string() }
+        };
+    }
 
     // check input content
     let input = serde_json::from_str::<Vec<Item>>(&para.from.content);
@@ -67,6 +93,100 @@ pub fn scatter(para: &ConverterParameter) -> ConverterReturned {
     ConverterReturned::Instances { ins: rtn }
 }
 
+fn scatter_nested(nested: &Nested, content: &str) -> Result<Vec<Instance>> {
+    let doc = serde_json::from_str::<Value>(content)
+        .map_err(|e| NatureError::VerifyError(format!("instance content error : {}", e)))?;
+    let value_path = to_tokens(&nested.value)?;
+    let mut dimensions: Vec<Vec<String>> = vec![];
+    for one in &nested.dimensions {
+        let tokens = to_tokens(one)?;
+        for (idx, token) in tokens.iter().enumerate() {
+            if token == "*" && (idx >= value_path.len() || tokens[..=idx] != value_path[..=idx]) {
+                let msg = format!("setting error : the \"*\" in dimension {} does not match the value path", one);
+                return Err(NatureError::VerifyError(msg));
+            }
+        }
+        dimensions.push(tokens);
+    }
+    let mut found: Vec<(Vec<String>, &Value)> = vec![];
+    expand(&doc, &value_path, vec![], &mut found);
+    let mut rtn: Vec<Instance> = Vec::with_capacity(found.len());
+    for (concrete, value) in found {
+        let mut parts: Vec<String> = Vec::with_capacity(dimensions.len());
+        for dimension in &dimensions {
+            parts.push(get_dimension(&doc, dimension, &concrete)?);
+        }
+        let mut ins = Instance::default();
+        ins.para = parts.join(&*SEPARATOR_INS_PARA);
+        ins.content = value.to_string();
+        rtn.push(ins);
+    }
+    Ok(rtn)
+}
+
+/// split json pointer into unescaped tokens
+fn to_tokens(path: &str) -> Result<Vec<String>> {
+    if path.is_empty() {
+        return Ok(vec![]);
+    }
+    if !path.starts_with('/') {
+        return Err(NatureError::VerifyError(format!("setting error : path must start with '/': {}", path)));
+    }
+    Ok(path[1..].split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// find all the values matched the path, `concrete` is the path with "*" replaced
+fn expand<'a>(current: &'a Value, path: &[String], concrete: Vec<String>, found: &mut Vec<(Vec<String>, &'a Value)>) {
+    let (token, rest) = match path.split_first() {
+        None => {
+            found.push((concrete, current));
+            return;
+        }
+        Some(first) => first
+    };
+    let next = |key: String, value: &'a Value, found: &mut Vec<(Vec<String>, &'a Value)>| {
+        let mut concrete = concrete.clone();
+        concrete.push(key);
+        expand(value, rest, concrete, found);
+    };
+    match (current, token.as_str()) {
+        (Value::Array(list), "*") => list.iter().enumerate().for_each(|(idx, v)| next(idx.to_string(), v, found)),
+        (Value::Object(map), "*") => map.iter().for_each(|(k, v)| next(k.to_string(), v, found)),
+        (Value::Array(list), idx) => if let Some(v) = idx.parse::<usize>().ok().and_then(|idx| list.get(idx)) {
+            next(token.to_string(), v, found)
+        },
+        (Value::Object(map), key) => if let Some(v) = map.get(key) {
+            next(token.to_string(), v, found)
+        },
+        _ => ()
+    }
+}
+
+fn get_dimension(doc: &Value, dimension: &[String], concrete: &[String]) -> Result<String> {
+    let mut current = doc;
+    for (idx, token) in dimension.iter().enumerate() {
+        let token = if token == "*" { &concrete[idx] } else { token };
+        if idx == dimension.len() - 1 && dimension[idx] == "*" {
+            return Ok(token.to_string());
+        }
+        let next = match current {
+            Value::Array(list) => token.parse::<usize>().ok().and_then(|idx| list.get(idx)),
+            Value::Object(map) => map.get(token),
+            _ => None
+        };
+        current = match next {
+            Some(v) => v,
+            None => return Err(NatureError::VerifyError(format!("can't find dimension /{} in content", dimension.join("/"))))
+        };
+    }
+    match current {
+        Value::String(s) => Ok(s.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        other => Err(NatureError::VerifyError(format!("can't use {} as dimension", other)))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -83,6 +203,7 @@ mod test {
 
         let setting = Setting {
             dimension_separator: "|".to_string(),
+            nested: None,
         };
 
         let para = ConverterParameter {
@@ -106,6 +227,7 @@ mod test {
     fn setting_default() {
         let set = Setting {
             dimension_separator: "/".to_string(),
+            nested: None,
         };
         let json = serde_json::to_string(&set).unwrap();
         let cmp = r#"{}"#;
@@ -114,6 +236,67 @@ mod test {
         assert_eq!(set.dimension_separator, "/")
     }
 
+    fn nested(cfg: &str, content: &str) -> ConverterReturned {
+        let mut from = Instance::default();
+        from.content = content.to_string();
+        let para = ConverterParameter {
+            from,
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: cfg.to_string(),
+        };
+        scatter(&para)
+    }
+
+    #[test]
+    fn nested_test() {
+        let cfg = r#"{"nested":{"dimensions":["/class","/students/*/name","/students/*/scores/*"],"value":"/students/*/scores/*"}}"#;
+        let content = r#"{"class":"c5","students":[
+            {"name":"n1","scores":{"math":92,"english":85}},
+            {"name":"n2","scores":{"math":{"score":60}}}
+        ]}"#;
+        match nested(cfg, content) {
+            ConverterReturned::Instances { ins } => {
+                assert_eq!(ins.len(), 3);
+                assert_eq!(ins[0].para, "c5/n1/english");
+                assert_eq!(ins[0].content, "85");
+                assert_eq!(ins[1].para, "c5/n1/math");
+                assert_eq!(ins[1].content, "92");
+                assert_eq!(ins[2].para, "c5/n2/math");
+                assert_eq!(ins[2].content, r#"{"score":60}"#);
+            }
+            _ => panic!("should return instances")
+        }
+    }
+
+    #[test]
+    fn nested_array_test() {
+        let cfg = r#"{"nested":{"dimensions":["/*/city","/*"],"value":"/*/amount"}}"#;
+        let content = r#"[{"city":"beijing","amount":5},{"city":"shanghai"},{"city":"xian","amount":7}]"#;
+        match nested(cfg, content) {
+            ConverterReturned::Instances { ins } => {
+                assert_eq!(ins.len(), 2);
+                assert_eq!(ins[0].para, "beijing/0");
+                assert_eq!(ins[0].content, "5");
+                assert_eq!(ins[1].para, "xian/2");
+            }
+            _ => panic!("should return instances")
+        }
+    }
+
+    #[test]
+    fn nested_error_test() {
+        let is_err = |cfg: &str, content: &str| matches!(nested(cfg, content), ConverterReturned::LogicalError { .. });
+        // "*" not in value path
+        assert!(is_err(r#"{"nested":{"dimensions":["/a/*"],"value":"/b/*"}}"#, r#"{"a":[1],"b":[1]}"#));
+        // dimension is not a scalar
+        assert!(is_err(r#"{"nested":{"dimensions":["/a"],"value":"/b/*"}}"#, r#"{"a":[1],"b":[1]}"#));
+        // dimension absent
+        assert!(is_err(r#"{"nested":{"dimensions":["/c"],"value":"/b/*"}}"#, r#"{"a":[1],"b":[1]}"#));
+        assert!(is_err(r#"{"nested":{"dimensions":[],"value":"b"}}"#, r#"{"b":1}"#));
+    }
+
     #[derive(Serialize)]
     struct KV {
         pub key: String,
@@ -1,9 +1,9 @@
 use std::ops::{Add, Sub};
 use std::str::FromStr;
 
-use chrono::{Date, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
 
-use crate::common::{ConverterParameter, ConverterReturned, get_para_and_key_from_para, Instance, is_default, NatureError, Result, SEPARATOR_INS_PARA};
+use crate::common::{ConverterParameter, ConverterReturned, get_para_and_key_from_para, Instance, is_default, NatureError, Result, SEPARATOR_INS_PARA, Zone};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct Setting {
@@ -27,20 +27,25 @@ struct Setting {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     time_part: u8,
-    // /// append something from upstream.para to downstream.para and set sys_context by name
-    // #[serde(skip_serializing_if = "is_default")]
-    // #[serde(default)]
-    // append: Vec<Append>,
-}
-
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-struct Append {
-    part: u8,
-    /// name for downstream.sys_context.DYNAMIC_PARA
-    name: String,
+    /// Hopping window, the slide step in `unit`, only for s,m,h,d. The window size is `value`,
+    /// so one instance may generate several overlapping windows. 0 means tumbling window.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    hop: i16,
+    /// Session window, the inactivity gap in `unit`, only for s,m,h,d. 0 means not a session window.
+    /// The target `Meta` must be a state meta and the key of the session must be set by
+    /// `RelationTarget.append_para`, so that every key has its own session state.
+    /// The start of the session is appended to the para, so every session has its own instance.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    session_gap: i16,
+    /// IANA name like "Asia/Shanghai" or fixed offset like "+08:00", used to decide the boundaries
+    /// of the windows. Empty means the time zone of the server, but s,m,h are aligned to the UTC epoch then.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    timezone: Zone,
 }
 
-
 /// generate a timer para
 pub fn time_range(input: &ConverterParameter) -> ConverterReturned {
     // get setting
@@ -67,13 +72,44 @@ pub fn time_range(input: &ConverterParameter) -> ConverterReturned {
     } else {
         input.from.create_time
     };
-    let result = match cfg.get_time(time_long) {
+    if cfg.session_gap > 0 {
+        return match cfg.get_session(time_long, &input.last_state) {
+            Ok(ins) => ConverterReturned::Instances { ins },
+            Err(err) => ConverterReturned::LogicalError { msg: err.to_string() }
+        };
+    }
+    let result = if cfg.hop > 0 {
+        cfg.get_hopping(time_long)
+    } else {
+        cfg.get_time(time_long).map(|rtn| vec![rtn])
+    };
+    let result = match result {
         Ok(rtn) => rtn,
         Err(err) => return ConverterReturned::LogicalError { msg: err.to_string() }
     };
-    let mut instance = Instance::default();
-    instance.para = format!("{}{}{}", result.0, *SEPARATOR_INS_PARA, result.1);
-    ConverterReturned::Instances { ins: vec![instance] }
+    let ins = result.into_iter().map(|one| {
+        let mut instance = Instance::default();
+        instance.para = format!("{}{}{}", one.0, *SEPARATOR_INS_PARA, one.1);
+        instance
+    }).collect();
+    ConverterReturned::Instances { ins }
+}
+
+/// whether the setting is a session window, used to verify the `Relation` when loading
+pub fn is_session(cfg: &str) -> bool {
+    match serde_json::from_str::<Setting>(cfg) {
+        Ok(cfg) => cfg.session_gap > 0,
+        Err(_) => false
+    }
+}
+
+/// the state of the session window, unit is second
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Session {
+    /// time of the first item in this session
+    start: i64,
+    /// the session will be closed at this time if no more items come
+    end: i64,
 }
 
 /// setting----------------------------------------------------
@@ -85,7 +121,7 @@ static DAY: i64 = 1000 * 60 * 60 * 24;
 
 impl Setting {
     fn get_time(&self, ins_time: i64) -> Result<(i64, i64)> {
-        let time = Local.timestamp_millis(ins_time).naive_local();
+        let time = self.timezone.to_naive(ins_time);
 
         let unit = self.unit.as_ref();
         let interval: i64 = if self.value == 0 {
@@ -94,24 +130,16 @@ impl Setting {
             self.value as i64
         };
         let rtn: (i64, i64) = match unit {
-            "s" => {
-                let rtn = ins_time / SECOND / interval * interval;
-                (rtn, rtn + interval)
-            }
-            "m" => {
-                let rtn = ins_time / MINUTE / interval * interval * MINUTE / SECOND;
-                (rtn, rtn + interval * MINUTE / SECOND)
-            }
-            "h" => {
-                let rtn = ins_time / HOUR / interval * interval * HOUR / SECOND;
-                (rtn, rtn + interval * HOUR / SECOND)
+            "s" | "m" | "h" => {
+                let size = interval * self.get_unit_millis()?;
+                let offset = self.align_offset(ins_time);
+                let rtn = (ins_time + offset).div_euclid(size) * size - offset;
+                (rtn / SECOND, (rtn + size) / SECOND)
             }
             "d" => {
-                let mut rtn = ins_time / DAY / interval * interval * DAY / SECOND;
-                // time zone process
-                let offset = Local.offset_from_local_date(&time.date()).unwrap().local_minus_utc();
-                rtn = rtn - offset as i64;
-                (rtn, rtn + interval * DAY / SECOND)
+                let day = days_from_epoch(&time.date());
+                let begin = day.div_euclid(interval) * interval;
+                (self.day_start_of(begin)? / SECOND, self.day_start_of(begin + interval)? / SECOND)
             }
             "w" => return self.get_week(&time),
             "M" => return self.get_month(&time),
@@ -124,6 +152,92 @@ impl Setting {
         Ok(rtn)
     }
 
+    /// align s,m,h to the wall clock of the `timezone`, or to the UTC epoch if it is not set
+    fn align_offset(&self, ins_time: i64) -> i64 {
+        match self.timezone {
+            Zone::Local => 0,
+            _ => self.timezone.offset_millis(ins_time)
+        }
+    }
+
+    /// millisecond of the fixed length unit
+    fn get_unit_millis(&self) -> Result<i64> {
+        match self.unit.as_ref() {
+            "s" => Ok(SECOND),
+            "m" => Ok(MINUTE),
+            "h" => Ok(HOUR),
+            "d" => Ok(DAY),
+            _ => Err(NatureError::LogicalError(format!("timer setting error: unit '{}' is not supported by hopping or session window", self.unit)))
+        }
+    }
+
+    /// milliseconds of the beginning of the day which is `day` days from 1970-01-01
+    fn day_start_of(&self, day: i64) -> Result<i64> {
+        let date = NaiveDate::from_ymd(1970, 1, 1).add(Duration::days(day));
+        self.timezone.day_start(&date)
+    }
+
+    /// all the windows which contain the `ins_time`, in ascending order
+    fn get_hopping(&self, ins_time: i64) -> Result<Vec<(i64, i64)>> {
+        let unit = self.get_unit_millis()?;
+        if self.value <= 0 {
+            return Err(NatureError::LogicalError("the `value` must great than 0 for hopping window".to_string()));
+        }
+        let mut rtn: Vec<(i64, i64)> = vec![];
+        if self.unit == "d" {
+            // the length of a day may be changed by DST, so calculate on date
+            let size = self.value as i64;
+            let slide = self.hop as i64;
+            let day = days_from_epoch(&self.timezone.to_naive(ins_time).date());
+            let mut start = day.div_euclid(slide) * slide;
+            while start + size > day {
+                rtn.push((self.day_start_of(start)? / SECOND, self.day_start_of(start + size)? / SECOND));
+                start -= slide;
+            }
+        } else {
+            let size = self.value as i64 * unit;
+            let slide = self.hop as i64 * unit;
+            let offset = self.align_offset(ins_time);
+            let local = ins_time + offset;
+            let mut start = local.div_euclid(slide) * slide;
+            while start + size > local {
+                rtn.push(((start - offset) / SECOND, (start + size - offset) / SECOND));
+                start -= slide;
+            }
+        }
+        rtn.reverse();
+        Ok(rtn)
+    }
+
+    /// merge the `ins_time` into the last session or create a new one
+    fn get_session(&self, ins_time: i64, last: &Option<Instance>) -> Result<Vec<Instance>> {
+        let gap = self.session_gap as i64 * self.get_unit_millis()? / SECOND;
+        let time = ins_time.div_euclid(SECOND);
+        let new = Session { start: time, end: time + gap };
+        let (para, session) = match last {
+            None => (new.start.to_string(), new),
+            Some(last_ins) => {
+                let last = serde_json::from_str::<Session>(&last_ins.content)?;
+                if time > last.end {
+                    // inactive too long, begin a new session
+                    (new.start.to_string(), new)
+                } else if time >= last.start - gap {
+                    // keep the para of the last session, even if the start is moved forward
+                    let para = last_ins.para.rsplit(&*SEPARATOR_INS_PARA).next().unwrap_or_default().to_string();
+                    (para, Session { start: last.start.min(time), end: last.end.max(time + gap) })
+                } else {
+                    // belongs to a closed session, ignore it
+                    warn!("time_range: the time {} is before the session {:?}, ignored", time, last);
+                    return Ok(vec![]);
+                }
+            }
+        };
+        let mut ins = Instance::default();
+        ins.para = para;
+        ins.content = serde_json::to_string(&session)?;
+        Ok(vec![ins])
+    }
+
     fn get_week(&self, nd: &NaiveDateTime) -> Result<(i64, i64)> {
         if self.value > 6 || self.value < -7 {
             return Err(NatureError::LogicalError("value must in [-7,6]".to_string()));
@@ -138,8 +252,9 @@ impl Setting {
         } else {
             7 - value + offset
         };
-        let rtn = Local.ymd(nd.year(), nd.month(), nd.day()).sub(Duration::days(diff_day as i64)).and_hms(0, 0, 0).timestamp_millis();
-        Ok((rtn, rtn + 7 * DAY))
+        let begin = nd.date().sub(Duration::days(diff_day as i64));
+        let end = begin.add(Duration::days(7));
+        Ok((self.timezone.day_start(&begin)?, self.timezone.day_start(&end)?))
     }
 
     fn get_month(&self, nd: &NaiveDateTime) -> Result<(i64, i64)> {
@@ -147,9 +262,10 @@ impl Setting {
         if self.value > 19 || self.value < -20 {
             return Err(NatureError::LogicalError("the `value` must in [-20,19]".to_string()));
         }
+        let zone = &self.timezone;
         let offset = nd.day0() as i16;
-        let this_month = Local.ymd(nd.year(), nd.month(), 1);
-        let next_month = get_next_month(&this_month.naive_local());
+        let this_month = NaiveDate::from_ymd(nd.year(), nd.month(), 1);
+        let next_month = get_next_month(&this_month);
         let mut value = self.value;
         if value < 0 {
             let days = next_month.sub(this_month).num_days();
@@ -157,25 +273,24 @@ impl Setting {
         }
         let rtn = if value <= offset {
             // `begin` in this month and `end` in next month
-            let begin = Local.ymd(nd.year(), nd.month(), (value + 1) as u32).and_hms(0, 0, 0);
-            let left = begin.timestamp_millis();
+            let begin = NaiveDate::from_ymd(nd.year(), nd.month(), (value + 1) as u32);
+            let left = zone.day_start(&begin)?;
             let right = if self.value >= 0 {
-                next_month.add(Duration::days(self.value as i64)).and_hms(0, 0, 0).timestamp_millis()
+                zone.day_start(&next_month.add(Duration::days(self.value as i64)))?
             } else {
-                let next_next = get_next_month(&next_month.naive_local());
-                let end = next_next.sub(Duration::days(-self.value as i64)).and_hms(0, 0, 0);
-                end.timestamp_millis()
+                let next_next = get_next_month(&next_month);
+                zone.day_start(&next_next.sub(Duration::days(-self.value as i64)))?
             };
             (left, right)
         } else {
             // `begin` in previous month and `end` in this month
             if self.value >= 0 {
-                let left = get_previous_month(&this_month.naive_local()).add(Duration::days(self.value as i64)).and_hms(0, 0, 0).timestamp_millis();
-                let right = this_month.add(Duration::days(self.value as i64)).and_hms(0, 0, 0).timestamp_millis();
+                let left = zone.day_start(&get_previous_month(&this_month).add(Duration::days(self.value as i64)))?;
+                let right = zone.day_start(&this_month.add(Duration::days(self.value as i64)))?;
                 (left, right)
             } else {
-                let left = this_month.sub(Duration::days(-self.value as i64)).and_hms(0, 0, 0).timestamp_millis();
-                let right = next_month.sub(Duration::days(-self.value as i64)).and_hms(0, 0, 0).timestamp_millis();
+                let left = zone.day_start(&this_month.sub(Duration::days(-self.value as i64)))?;
+                let right = zone.day_start(&next_month.sub(Duration::days(-self.value as i64)))?;
                 (left, right)
             }
         };
@@ -186,8 +301,8 @@ impl Setting {
         if self.value > 199 || self.value < -200 {
             return Err(NatureError::LogicalError("value must in [-7,6]".to_string()));
         }
-        let year_begin = Local.ymd(nd.year(), 1, 1);
-        let today = Local.ymd(nd.year(), nd.month(), nd.day());
+        let year_begin = NaiveDate::from_ymd(nd.year(), 1, 1);
+        let today = nd.date();
         let offset = today.sub(year_begin).num_days() as i16;
         let mut value = self.value;
         if value < 0 {
@@ -198,14 +313,14 @@ impl Setting {
         } else {
             365 - value + offset
         };
-        let left = today.sub(Duration::days(diff_day as i64)).and_hms(0, 0, 0);
+        let left = today.sub(Duration::days(diff_day as i64));
         let right = if self.value >= 0 {
-            Local.ymd(left.year() + 1, left.month(), left.day())
+            NaiveDate::from_ymd(left.year() + 1, left.month(), left.day())
         } else {
-            let end = Local.ymd(left.year() + 2, 1, 1);
+            let end = NaiveDate::from_ymd(left.year() + 2, 1, 1);
             end.sub(Duration::days(-self.value as i64))
         };
-        Ok((left.timestamp_millis(), right.and_hms(0, 0, 0).timestamp_millis()))
+        Ok((self.timezone.day_start(&left)?, self.timezone.day_start(&right)?))
     }
 }
 
@@ -216,23 +331,30 @@ impl Default for Setting {
             value: 1,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         }
     }
 }
 
-fn get_next_month(nd: &NaiveDate) -> Date<Local> {
+fn days_from_epoch(date: &NaiveDate) -> i64 {
+    date.sub(NaiveDate::from_ymd(1970, 1, 1)).num_days()
+}
+
+fn get_next_month(nd: &NaiveDate) -> NaiveDate {
     if nd.month() < 12 {
-        Local.ymd(nd.year(), nd.month() + 1, 1)
+        NaiveDate::from_ymd(nd.year(), nd.month() + 1, 1)
     } else {
-        Local.ymd(nd.year() + 1, 1, 1)
+        NaiveDate::from_ymd(nd.year() + 1, 1, 1)
     }
 }
 
-fn get_previous_month(nd: &NaiveDate) -> Date<Local> {
+fn get_previous_month(nd: &NaiveDate) -> NaiveDate {
     if nd.month() > 1 {
-        Local.ymd(nd.year(), nd.month() - 1, 1)
+        NaiveDate::from_ymd(nd.year(), nd.month() - 1, 1)
     } else {
-        Local.ymd(nd.year() - 1, 12, 1)
+        NaiveDate::from_ymd(nd.year() - 1, 12, 1)
     }
 }
 
@@ -247,6 +369,8 @@ fn string_s() -> String {
 
 #[cfg(test)]
 mod timer_setting_test {
+    use chrono::{Local, TimeZone};
+
     use super::*;
 
     #[ignore]
@@ -263,6 +387,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = serde_json::to_string(&setting).unwrap();
         assert_eq!(rtn, "{}");
@@ -285,6 +412,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -310,6 +440,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -335,6 +468,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -360,6 +496,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -391,6 +530,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -440,6 +582,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -495,6 +640,9 @@ mod timer_setting_test {
             value: 0,
             on_para: false,
             time_part: 0,
+            hop: 0,
+            session_gap: 0,
+            timezone: Zone::Local,
         };
         let rtn = setting.get_time(time).unwrap();
         let cmp = (
@@ -542,3 +690,145 @@ mod timer_setting_test {
     }
 }
 
+
+#[cfg(test)]
+mod window_test {
+    use std::str::FromStr;
+
+    use chrono::{Local, TimeZone, Utc};
+
+    use super::*;
+
+    fn setting(unit: &str, value: i16, hop: i16, session_gap: i16) -> Setting {
+        Setting {
+            unit: unit.to_string(),
+            value,
+            on_para: false,
+            time_part: 0,
+            hop,
+            session_gap,
+            timezone: Zone::Local,
+        }
+    }
+
+    #[test]
+    fn hopping_test() {
+        let time = Local.ymd(2020, 5, 1).and_hms_milli(18, 36, 23, 123).timestamp_millis();
+        let rtn = setting("m", 10, 5, 0).get_hopping(time).unwrap();
+        let cmp = vec![
+            (Local.ymd(2020, 5, 1).and_hms(18, 30, 0).timestamp(), Local.ymd(2020, 5, 1).and_hms(18, 40, 0).timestamp()),
+            (Local.ymd(2020, 5, 1).and_hms(18, 35, 0).timestamp(), Local.ymd(2020, 5, 1).and_hms(18, 45, 0).timestamp()),
+        ];
+        assert_eq!(rtn, cmp);
+        // hop great than size, the time may be out of all windows
+        let rtn = setting("m", 1, 5, 0).get_hopping(time).unwrap();
+        assert!(rtn.is_empty());
+        // unsupported unit
+        assert!(setting("w", 1, 1, 0).get_hopping(time).is_err());
+        assert!(setting("m", 0, 1, 0).get_hopping(time).is_err());
+    }
+
+    #[test]
+    fn rolling_days_test() {
+        let time = Local.ymd(2020, 5, 1).and_hms_milli(18, 36, 23, 123).timestamp_millis();
+        let rtn = setting("d", 7, 1, 0).get_hopping(time).unwrap();
+        assert_eq!(rtn.len(), 7);
+        assert_eq!(rtn[0], (Local.ymd(2020, 4, 25).and_hms(0, 0, 0).timestamp(), Local.ymd(2020, 5, 2).and_hms(0, 0, 0).timestamp()));
+        assert_eq!(rtn[6], (Local.ymd(2020, 5, 1).and_hms(0, 0, 0).timestamp(), Local.ymd(2020, 5, 8).and_hms(0, 0, 0).timestamp()));
+    }
+
+    #[test]
+    fn hopping_instances_test() {
+        let input = ConverterParameter {
+            from: {
+                let mut ins = Instance::default();
+                ins.create_time = Utc.ymd(2020, 5, 1).and_hms(18, 36, 23).timestamp_millis();
+                ins
+            },
+            last_state: None,
+            task_id: 0,
+            master: None,
+            cfg: r#"{"unit":"h","value":2,"hop":1}"#.to_string(),
+        };
+        match time_range(&input) {
+            ConverterReturned::Instances { ins } => {
+                assert_eq!(ins.len(), 2);
+                let begin = Utc.ymd(2020, 5, 1).and_hms(17, 0, 0).timestamp();
+                assert_eq!(ins[0].para, format!("{}/{}", begin, begin + 7200));
+                assert_eq!(ins[1].para, format!("{}/{}", begin + 3600, begin + 10800));
+            }
+            _ => panic!("should return instances")
+        }
+    }
+
+    #[test]
+    fn session_test() {
+        let cfg = setting("m", 0, 0, 30);
+        let last = |start: i64, end: i64| {
+            let mut ins = Instance::default();
+            ins.para = format!("user1/{}", start);
+            ins.content = serde_json::to_string(&Session { start, end }).unwrap();
+            Some(ins)
+        };
+        let session = |rtn: &Vec<Instance>| serde_json::from_str::<Session>(&rtn[0].content).unwrap();
+        // new session
+        let rtn = cfg.get_session(10_000_500, &None).unwrap();
+        assert_eq!(session(&rtn), Session { start: 10_000, end: 11_800 });
+        assert_eq!(rtn[0].para, "10000");
+        // extend the session
+        let rtn = cfg.get_session(11_000_000, &last(10_000, 11_800)).unwrap();
+        assert_eq!(session(&rtn), Session { start: 10_000, end: 12_800 });
+        assert_eq!(rtn[0].para, "10000");
+        // out of order but still in the session
+        let rtn = cfg.get_session(9_000_000, &last(10_000, 11_800)).unwrap();
+        assert_eq!(session(&rtn), Session { start: 9_000, end: 11_800 });
+        assert_eq!(rtn[0].para, "10000");
+        // inactive too long
+        let rtn = cfg.get_session(12_000_000, &last(10_000, 11_800)).unwrap();
+        assert_eq!(session(&rtn), Session { start: 12_000, end: 13_800 });
+        assert_eq!(rtn[0].para, "12000");
+        // belongs to a closed session
+        let rtn = cfg.get_session(8_000_000, &last(10_000, 11_800)).unwrap();
+        assert!(rtn.is_empty());
+    }
+
+    #[test]
+    fn timezone_day_test() {
+        // 2020-05-01 18:36:23 UTC is 2020-05-02 02:36:23 in Shanghai
+        let time = 1588358183000;
+        let mut cfg = setting("d", 1, 0, 0);
+        cfg.timezone = Zone::from_str("Asia/Shanghai").unwrap();
+        assert_eq!(cfg.get_time(time).unwrap(), (1588348800, 1588435200));
+        cfg.timezone = Zone::from_str("+00:00").unwrap();
+        assert_eq!(cfg.get_time(time).unwrap(), (1588291200, 1588377600));
+        // hour aligned to the wall clock for the time zone with half an hour offset
+        let mut cfg = setting("h", 1, 0, 0);
+        cfg.timezone = Zone::from_str("+05:30").unwrap();
+        assert_eq!(cfg.get_time(time).unwrap(), (1588357800, 1588361400));
+        // aligned to the UTC epoch when the time zone is not set, whatever the server's is
+        let cfg = setting("h", 6, 0, 0);
+        assert_eq!(cfg.get_time(time).unwrap(), (1588356000, 1588377600));
+        assert_eq!(setting("h", 6, 3, 0).get_hopping(time).unwrap(), vec![(1588345200, 1588366800), (1588356000, 1588377600)]);
+    }
+
+    #[test]
+    fn timezone_dst_test() {
+        // 2020-03-08 13:00 in New York, DST begins at this day
+        let time = 1583686800000;
+        let mut cfg = setting("d", 1, 0, 0);
+        cfg.timezone = Zone::from_str("America/New_York").unwrap();
+        let (begin, end) = cfg.get_time(time).unwrap();
+        // 2020-03-08 05:00 UTC to 2020-03-09 04:00 UTC
+        assert_eq!((begin, end), (1583643600, 1583726400));
+        assert_eq!(end - begin, 23 * 3600);
+        // week and month are decided by the time zone too
+        let mut cfg = setting("w", 0, 0, 0);
+        cfg.timezone = Zone::from_str("America/New_York").unwrap();
+        let (begin, end) = cfg.get_time(time).unwrap();
+        // 2020-03-02 05:00 UTC to 2020-03-09 04:00 UTC
+        assert_eq!((begin, end), (1583125200000, 1583726400000));
+        let mut cfg = setting("M", 0, 0, 0);
+        cfg.timezone = Zone::from_str("UTC").unwrap();
+        assert_eq!(cfg.get_time(time).unwrap(), (1583020800000, 1585699200000));
+    }
+}
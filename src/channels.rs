@@ -1,43 +1,95 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::*;
 use std::sync::Mutex;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-use crate::controller::channel_convert;
+use crate::common::DeadLetter;
+use crate::controller::{channel_convert, channel_dead_letter};
 use crate::db::*;
 use crate::task::*;
 
 lazy_static! {
     pub static ref CHANNEL_CONVERT : Channel<(TaskForConvert,RawTask)> = Channel::new();
+    pub static ref CHANNEL_DEAD_LETTER : Channel<DeadLetter> = Channel::new();
 }
 
-pub fn start_receive_threads() -> Vec<JoinHandle<()>> {
-    let mut threads: Vec<JoinHandle<()>> = Vec::new();
-    threads.push(start_thread(&CHANNEL_CONVERT.receiver, channel_convert));
+pub fn start_receive_threads() -> ReceiveThreads {
+    let rtn = ReceiveThreads {
+        convert: start_thread(&CHANNEL_CONVERT, channel_convert),
+        dead_letter: start_dead_letter_thread(channel_dead_letter),
+    };
     info!("--------------------nature threads initialized---------------------");
-    threads
+    rtn
 }
 
+/// the convert thread should be stopped before the dead letter thread,
+/// because the failed convert tasks will generate dead letters.
+pub struct ReceiveThreads {
+    pub convert: JoinHandle<()>,
+    pub dead_letter: JoinHandle<()>,
+}
+
+/// the `retry` program needs this only, and gives its own handler.
+pub fn start_dead_letter_thread<F>(f: F) -> JoinHandle<()>
+    where F: 'static + Fn(DeadLetter) + Send
+{
+    start_thread(&CHANNEL_DEAD_LETTER, f)
+}
 
-fn start_thread<T, F>(receiver: &'static Mutex<Receiver<T>>, f: F) -> JoinHandle<()>
+
+fn start_thread<T, F>(channel: &'static Channel<T>, f: F) -> JoinHandle<()>
     where
         T: Send,
         F: 'static + Fn(T) + Send
 {
     use std::ops::Deref;
     thread::spawn(move || {
-        let guard = receiver.lock().unwrap();
+        let _running = Running::new(&channel.running);
+        let guard = channel.receiver.lock().unwrap();
         let receiver = guard.deref();
-        for next in receiver {
-            f(next);
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(next) => {
+                    channel.depth.fetch_sub(1, Ordering::SeqCst);
+                    f(next)
+                }
+                Err(RecvTimeoutError::Timeout) => if channel.stopping.load(Ordering::SeqCst) {
+                    break;
+                },
+                Err(RecvTimeoutError::Disconnected) => break
+            }
         }
     })
 }
 
 
+/// count the alive threads, works even if the thread panicked
+struct Running(&'static AtomicUsize);
+
+impl Running {
+    fn new(counter: &'static AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Running(counter)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 pub struct Channel<T> {
     pub sender: Mutex<Sender<T>>,
     pub receiver: Mutex<Receiver<T>>,
+    /// messages waiting to be received
+    depth: AtomicUsize,
+    /// number of the alive threads receiving this channel
+    running: AtomicUsize,
+    /// the receive threads will exit after the channel is drained
+    stopping: AtomicBool,
 }
 
 impl<T> Channel<T> {
@@ -46,7 +98,33 @@ impl<T> Channel<T> {
         Channel {
             sender: Mutex::new(sx),
             receiver: Mutex::new(rx),
+            depth: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            stopping: AtomicBool::new(false),
+        }
+    }
+
+    /// use this instead of `sender` to make the `depth` correct
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.depth.fetch_add(1, Ordering::SeqCst);
+        let rtn = self.sender.lock().unwrap().send(msg);
+        if rtn.is_err() {
+            self.depth.fetch_sub(1, Ordering::SeqCst);
         }
+        rtn
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst)
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// let the receive threads exit when there are no more messages in this channel
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
     }
 }
 
@@ -54,4 +132,27 @@ impl<T> Default for Channel<T> {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    lazy_static! {
+        static ref CHANNEL_TEST : Channel<u8> = Channel::new();
+    }
+
+    #[test]
+    fn depth_test() {
+        CHANNEL_TEST.send(1).unwrap();
+        CHANNEL_TEST.send(2).unwrap();
+        assert_eq!(CHANNEL_TEST.depth(), 2);
+        let thread = start_thread(&CHANNEL_TEST, |_| ());
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(CHANNEL_TEST.depth(), 0);
+        assert_eq!(CHANNEL_TEST.running(), 1);
+        CHANNEL_TEST.stop();
+        thread.join().unwrap();
+        assert_eq!(CHANNEL_TEST.running(), 0);
+    }
+}
@@ -1,5 +1,7 @@
 pub use callback::*;
+pub use config::*;
 pub use converter::*;
+pub use dead_letter::*;
 pub use error::*;
 pub use from_instance::*;
 pub use instance::*;
@@ -8,15 +10,22 @@ pub use loop_context::*;
 pub use meta_setting::*;
 pub use meta_type::*;
 pub use query::*;
+pub use readiness::*;
+pub use retry_policy::*;
 pub use settings::*;
 pub use state::*;
+pub use state_history::*;
+pub use state_update::*;
 pub use target_state::*;
+pub use time_zone::*;
 pub use util::*;
 
 pub use self::meta::*;
 
 mod callback;
+mod config;
 mod converter;
+mod dead_letter;
 mod error;
 mod from_instance;
 mod instance;
@@ -26,9 +35,14 @@ mod meta;
 mod meta_setting;
 mod meta_type;
 mod query;
+mod readiness;
+mod retry_policy;
 mod settings;
 mod state;
+mod state_history;
+mod state_update;
 mod target_state;
+mod time_zone;
 mod util;
 
 pub type Result<T> = std::result::Result<T, NatureError>;
@@ -16,7 +16,7 @@ pub enum ConverterReturned {
     None,
     /// Tell `Nature` the task will be processed asynchronously, Nature will wait for seconds you assigned, and converter will callback to `Nature` later while result are ready.
     Delay { num: u32 },
-    /// return instances
+    /// return instances, empty means nothing need to output and the task will be finished.
     Instances { ins: Vec<Instance> },
     /// return `SelfRouteInstance`
     SelfRoute { ins: Vec<SelfRouteInstance> },
@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use serde_json::Value;
+
+/// sent to the dead-letter webhook and used as the content of the dead-letter `Instance`
+/// when a task is moved to the `task_error` table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    pub task_id: u64,
+    pub task_key: String,
+    /// same as `TaskType`: 1 store, 2 convert, 11 batch
+    pub task_type: i8,
+    pub task_for: String,
+    pub msg: String,
+    /// the task data decoded from json, or the raw string if it is not a json
+    pub data: Value,
+    pub create_time: NaiveDateTime,
+}
+
+impl DeadLetter {
+    pub fn decode(data: &str) -> Value {
+        match serde_json::from_str(data) {
+            Ok(v) => v,
+            Err(_) => Value::String(data.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(DeadLetter::decode(r#"{"a":1}"#), serde_json::json!({"a":1}));
+        assert_eq!(DeadLetter::decode("a"), Value::String("a".to_string()));
+    }
+}
@@ -1,5 +1,33 @@
+use chrono::NaiveDateTime;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LoopContext {
     pub next: String,
     pub len: usize,
-}
\ No newline at end of file
+}
+
+/// progress of an unfinished `MetaType::Loop`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoopProgress {
+    /// the task which will execute the next iteration
+    pub task_id: u64,
+    /// key of the `Instance` which the next iteration will begin from
+    pub task_key: String,
+    /// the `MetaType::Loop` meta
+    pub task_for: String,
+    /// the `loop.next` cursor, `None` means the loop has not loaded any page
+    pub next: Option<String>,
+    /// how many iterations have finished
+    pub iterations: u32,
+    pub retried_times: i16,
+    pub create_time: NaiveDateTime,
+    pub execute_time: NaiveDateTime,
+}
+
+/// restart the loop task from the given key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoopResume {
+    pub task_id: u64,
+    /// used as `loop.next`, the data will be loaded from the key great than it
+    pub next: String,
+}
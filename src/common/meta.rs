@@ -251,6 +251,7 @@ impl Meta {
             if setting.is_state {
                 self.is_state = true;
             }
+            self.check_setting_states(&setting)?;
             self.setting = Some(setting);
         } else {
             self.setting = None;
@@ -258,6 +259,74 @@ impl Meta {
         Ok(())
     }
 
+    /// states used in setting must be defined
+    fn check_setting_states(&self, setting: &MetaSetting) -> Result<()> {
+        if setting.transitions.is_empty() && setting.terminal_states.is_empty() {
+            return Ok(());
+        }
+        if self.state.is_none() {
+            return Err(VerifyError(format!("[{}] has no states defined for transitions or terminal_states", self.meta)));
+        }
+        let mut names = setting.terminal_states.iter();
+        if let Some(name) = names.find(|n| !self.has_state_name(n)) {
+            return Err(VerifyError(format!("terminal state [{}] does not defined in meta: {}", name, self.meta)));
+        }
+        for (from, to) in &setting.transitions {
+            if let Some(name) = to.iter().chain(std::iter::once(from)).find(|n| !self.has_state_name(n)) {
+                return Err(VerifyError(format!("transition state [{}] does not defined in meta: {}", name, self.meta)));
+            }
+        }
+        Ok(())
+    }
+
+    /// whether `states` include any terminal state
+    pub fn is_terminal(&self, states: &HashSet<String>) -> bool {
+        match &self.setting {
+            Some(s) => s.terminal_states.iter().any(|t| states.contains(t)),
+            None => false
+        }
+    }
+
+    /// return error if `states` include any terminal state, so that no more version could be generated.
+    pub fn check_terminal(&self, states: &HashSet<String>) -> Result<()> {
+        if !self.is_terminal(states) {
+            return Ok(());
+        }
+        let mut states: Vec<&String> = states.iter().collect();
+        states.sort();
+        let msg = format!("instance of meta {} is in terminal state {:?}, no more version allowed", self.meta, states);
+        warn!("{}", &msg);
+        Err(NatureError::LogicalError(msg))
+    }
+
+    /// A newly added state which is guarded by transitions must follow one of the `last` states.
+    pub fn check_transition(&self, last: &HashSet<String>, next: &HashSet<String>) -> Result<()> {
+        let transitions = match &self.setting {
+            Some(s) if !s.transitions.is_empty() => &s.transitions,
+            _ => return Ok(())
+        };
+        let mut added: Vec<&String> = next.difference(last).collect();
+        added.sort();
+        for to in added {
+            let guarded = transitions.values().any(|v| v.contains(to));
+            if !guarded {
+                continue;
+            }
+            let allowed = last.iter().any(|from| match transitions.get(from) {
+                Some(v) => v.contains(to),
+                None => false
+            });
+            if !allowed {
+                let mut from: Vec<&String> = last.iter().collect();
+                from.sort();
+                let msg = format!("illegal state transition for meta {}: {:?} -> [{}]", self.meta, from, to);
+                warn!("{}", &msg);
+                return Err(NatureError::LogicalError(msg));
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_setting(&self) -> Option<MetaSetting> {
         self.setting.clone()
     }
@@ -415,6 +484,9 @@ mod verify_test {
             multi_meta: Default::default(),
             cache_saved: false,
             only_one: false,
+            transitions: Default::default(),
+            terminal_states: Default::default(),
+            retry: None,
         }.to_json().unwrap();
         let _ = meta.set_setting(&setting);
         let set: Vec<String> = vec!["a".to_string()];
@@ -499,3 +571,71 @@ mod verify_test {
         assert_eq!(rtn.contains(&"e".to_string()), true);
     }
 }
+
+#[cfg(test)]
+mod transition_test {
+    use super::*;
+
+    fn init_meta() -> Meta {
+        let mut meta = Meta::new("/order", 1, MetaType::Business).unwrap();
+        let (ss, _) = State::string_to_states("new,paid,sent,cancelled").unwrap();
+        let _ = meta.set_states(Some(ss));
+        meta.set_setting(r#"{"transitions":{"new":["paid","cancelled"],"paid":["sent"]}}"#).unwrap();
+        meta
+    }
+
+    fn set(states: &[&str]) -> HashSet<String> {
+        states.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn undefined_state_test() {
+        let mut meta = Meta::new("/order", 1, MetaType::Business).unwrap();
+        let rtn = meta.set_setting(r#"{"is_state":true,"transitions":{"new":["paid"]}}"#);
+        assert!(rtn.is_err());
+        let (ss, _) = State::string_to_states("new").unwrap();
+        let _ = meta.set_states(Some(ss));
+        let rtn = meta.set_setting(r#"{"transitions":{"new":["paid"]}}"#);
+        assert_eq!(rtn, Err(VerifyError("transition state [paid] does not defined in meta: B:order:1".to_string())));
+    }
+
+    #[test]
+    fn allowed_test() {
+        let meta = init_meta();
+        assert!(meta.check_transition(&set(&[]), &set(&["new"])).is_ok());
+        assert!(meta.check_transition(&set(&["new"]), &set(&["paid"])).is_ok());
+        assert!(meta.check_transition(&set(&["paid"]), &set(&["paid", "sent"])).is_ok());
+        assert!(meta.check_transition(&set(&["paid"]), &set(&["paid"])).is_ok());
+    }
+
+    #[test]
+    fn illegal_test() {
+        let meta = init_meta();
+        let rtn = meta.check_transition(&set(&[]), &set(&["paid"]));
+        assert_eq!(rtn, Err(NatureError::LogicalError("illegal state transition for meta B:order:1: [] -> [paid]".to_string())));
+        let rtn = meta.check_transition(&set(&["new"]), &set(&["sent"]));
+        assert_eq!(rtn, Err(NatureError::LogicalError("illegal state transition for meta B:order:1: [\"new\"] -> [sent]".to_string())));
+    }
+}
+
+#[cfg(test)]
+mod terminal_test {
+    use super::*;
+
+    #[test]
+    fn terminal_test() {
+        let mut meta = Meta::new("/order", 1, MetaType::Business).unwrap();
+        let (ss, _) = State::string_to_states("new,cancelled").unwrap();
+        let _ = meta.set_states(Some(ss));
+        let rtn = meta.set_setting(r#"{"terminal_states":["closed"]}"#);
+        assert_eq!(rtn, Err(VerifyError("terminal state [closed] does not defined in meta: B:order:1".to_string())));
+        meta.set_setting(r#"{"terminal_states":["cancelled"]}"#).unwrap();
+        let mut states: HashSet<String> = HashSet::new();
+        states.insert("new".to_string());
+        assert!(!meta.is_terminal(&states));
+        assert!(meta.check_terminal(&states).is_ok());
+        states.insert("cancelled".to_string());
+        assert!(meta.is_terminal(&states));
+        assert!(meta.check_terminal(&states).is_err());
+    }
+}
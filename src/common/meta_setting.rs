@@ -1,7 +1,8 @@
+use std::collections::btree_map::BTreeMap;
 use std::collections::btree_set::BTreeSet;
 use std::str::FromStr;
 
-use crate::common::{FromInstance, Instance, is_default, NatureError, Result};
+use crate::common::{FromInstance, Instance, is_default, NatureError, Result, RetryPolicy};
 
 #[derive(Debug, Clone, Default, PartialEq, Ord, PartialOrd, Eq)]
 #[derive(Serialize, Deserialize)]
@@ -21,6 +22,17 @@ pub struct MetaSetting {
     /// only used by `MetaType::Loop`, has only one instance generated when loop finished.
     /// Requirement: multi_meta should has only one item
     pub only_one: bool,
+    /// Only useful for state-meta.
+    /// key is the from-state, value is the states allowed to follow it.
+    /// A state which is not in any value can follow any state.
+    pub transitions: BTreeMap<String, BTreeSet<String>>,
+    /// Only useful for state-meta.
+    /// Once an instance has one of these states, no more version can be generated for it,
+    /// and only the relations marked `after_terminal` will be fired for it,
+    /// including the version which enters the terminal state.
+    pub terminal_states: BTreeSet<String>,
+    /// used by the relations which target to this meta and have no `retry` setting.
+    pub retry: Option<RetryPolicy>,
 }
 
 impl From<MetaSettingTemp> for MetaSetting {
@@ -35,6 +47,10 @@ impl From<MetaSettingTemp> for MetaSetting {
             },
             cache_saved: input.cache_saved,
             only_one: input.only_one,
+            transitions: input.transitions.into_iter()
+                .map(|(k, v)| (k, v.into_iter().collect())).collect(),
+            terminal_states: input.terminal_states.into_iter().collect(),
+            retry: input.retry,
         }
     }
 }
@@ -44,6 +60,9 @@ impl FromStr for MetaSetting {
 
     fn from_str(s: &str) -> Result<Self> {
         let tmp: MetaSettingTemp = serde_json::from_str(s)?;
+        if let Some(retry) = &tmp.retry {
+            retry.verify()?;
+        }
         Ok(tmp.into())
     }
 }
@@ -60,6 +79,10 @@ impl From<MetaSetting> for MetaSettingTemp {
             },
             cache_saved: input.cache_saved,
             only_one: input.only_one,
+            transitions: input.transitions.into_iter()
+                .map(|(k, v)| (k, v.into_iter().collect())).collect(),
+            terminal_states: input.terminal_states.into_iter().collect(),
+            retry: input.retry,
         }
     }
 }
@@ -115,6 +138,15 @@ struct MetaSettingTemp {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     pub only_one: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub transitions: BTreeMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub terminal_states: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
 }
 
 #[cfg(test)]
@@ -141,6 +173,9 @@ mod test {
             multi_meta: set,
             cache_saved: false,
             only_one: false,
+            transitions: Default::default(),
+            terminal_states: Default::default(),
+            retry: None,
         };
         let a = Instance::new("a").unwrap();
         let b = Instance::new("b").unwrap();
@@ -167,6 +202,9 @@ mod test {
             multi_meta: set,
             cache_saved: false,
             only_one: false,
+            transitions: Default::default(),
+            terminal_states: Default::default(),
+            retry: None,
         };
         let a = Instance::default();
         let b = Instance::default();
@@ -185,4 +223,20 @@ mod test {
         let result = MetaSetting::from(result);
         assert_eq!(result.cache_saved, true);
     }
+
+    #[test]
+    fn transitions_test() {
+        let setting = r#"{"is_state":true,"transitions":{"new":["paid","cancelled"]}}"#;
+        let result = MetaSetting::from_str(setting).unwrap();
+        assert_eq!(result.transitions.get("new").unwrap().len(), 2);
+        assert_eq!(result.to_json().unwrap(), r#"{"is_state":true,"transitions":{"new":["cancelled","paid"]}}"#);
+    }
+
+    #[test]
+    fn terminal_states_test() {
+        let setting = r#"{"terminal_states":["closed","cancelled"]}"#;
+        let result = MetaSetting::from_str(setting).unwrap();
+        assert!(result.terminal_states.contains("closed"));
+        assert_eq!(result.to_json().unwrap(), r#"{"terminal_states":["cancelled","closed"]}"#);
+    }
 }
\ No newline at end of file
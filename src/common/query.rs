@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::common::{FromInstance, Instance, is_default, is_one, one, SEPARATOR_INS_KEY};
 
 /// used for query instance by id
@@ -114,12 +116,15 @@ impl IDAndFrom {
     }
 }
 
-/// used for query instance by id
+/// used for query instances which belong to a `Meta`
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct QueryByMeta {
     pub meta: String,
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
     pub para_like: Option<String>,
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
@@ -129,8 +134,90 @@ pub struct QueryByMeta {
     pub create_time_ge: Option<i64>,
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
+    pub create_time_lt: Option<i64>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
     pub create_time_desc: bool,
+    /// instance must have all of these states
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub states_include: Vec<String>,
+    /// instance must have none of these states
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub states_exclude: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub state_version_ge: Option<i32>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub state_version_le: Option<i32>,
+    /// each of the items must be equal to the value of the same key in `Instance.context`
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub context: BTreeMap<String, String>,
+    /// the `next` returned by the previous page, `None` means the first page
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub cursor: Option<QueryCursor>,
+    #[serde(skip_serializing_if = "is_one")]
+    #[serde(default = "one")]
+    pub limit: i32,
+}
 
+impl QueryByMeta {
+    pub fn new(meta: &str) -> Self {
+        QueryByMeta {
+            meta: meta.to_string(),
+            id: None,
+            para_like: None,
+            create_time_gt: None,
+            create_time_ge: None,
+            create_time_lt: None,
+            create_time_desc: false,
+            states_include: vec![],
+            states_exclude: vec![],
+            state_version_ge: None,
+            state_version_le: None,
+            context: BTreeMap::new(),
+            cursor: None,
+            limit: 1,
+        }
+    }
+}
+
+/// position of the last instance in a page, instances are ordered by these fields.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct QueryCursor {
+    pub create_time: i64,
+    pub id: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub para: String,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub state_version: i32,
+}
+
+impl From<&Instance> for QueryCursor {
+    fn from(input: &Instance) -> Self {
+        QueryCursor {
+            create_time: input.create_time,
+            id: input.id,
+            para: input.para.to_string(),
+            state_version: input.state_version,
+        }
+    }
+}
+
+/// one page of the `QueryByMeta` result
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct InstancePage {
+    pub list: Vec<Instance>,
+    /// used as `QueryByMeta.cursor` to get the next page, `None` means no more data.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub next: Option<QueryCursor>,
 }
 
 #[cfg(test)]
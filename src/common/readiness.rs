@@ -0,0 +1,15 @@
+/// returned by the `/ready` interface
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Readiness {
+    pub ready: bool,
+    /// whether the database is reachable
+    pub db: bool,
+    /// number of the running convert threads
+    pub workers: usize,
+    /// tasks waiting in the convert queue
+    pub queue_depth: usize,
+    /// why it is not ready
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub problems: Vec<String>,
+}
@@ -0,0 +1,147 @@
+use crate::common::{is_default, NatureError, Result};
+
+/// how the retry interval grows
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Backoff {
+    /// always `interval`
+    Fixed,
+    /// `interval` * (retried times + 1)
+    Linear,
+    /// `interval` * 2 ^ retried times
+    #[default]
+    Exponential,
+}
+
+/// what to do with the task when it tried `max_attempts` times
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GiveUp {
+    /// move the task to the `task_error` table
+    #[default]
+    Error,
+    /// delete the task, for the executors whose failure can be ignored
+    Drop,
+}
+
+/// used by `RelationSettings` and `MetaSetting`, the default one behaves like the retry program's settings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Ord, PartialOrd)]
+pub struct RetryPolicy {
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// seconds, 0 means `FIRST_RETRY_INTERVAL`
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub interval: i32,
+    /// seconds, the interval won't be greater than it, 0 means no limit
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub max_interval: i32,
+    /// percent of the interval to add or subtract randomly, so that the failed tasks won't be retried at the same time
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub jitter: u8,
+    /// 0 means `MAX_RETRY_TIMES`
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub max_attempts: u16,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub give_up: GiveUp,
+}
+
+impl RetryPolicy {
+    pub fn verify(&self) -> Result<()> {
+        if self.interval < 0 || self.max_interval < 0 {
+            return Err(NatureError::VerifyError("retry interval can't be negative".to_string()));
+        }
+        if self.jitter > 100 {
+            return Err(NatureError::VerifyError("retry jitter should be in 0..=100".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn max_attempts(&self, default: usize) -> usize {
+        match self.max_attempts {
+            0 => default,
+            n => n as usize
+        }
+    }
+
+    /// seconds to wait after `times` retries.
+    /// `random` is used for the jitter, the same `random` gives the same result.
+    pub fn delay(&self, times: i16, default_interval: i32, random: u32) -> i32 {
+        let interval = (if self.interval > 0 { self.interval } else { default_interval }) as i64;
+        let times = times.max(0) as i64;
+        let mut rtn = match self.backoff {
+            Backoff::Fixed => interval,
+            Backoff::Linear => interval.saturating_mul(times + 1),
+            Backoff::Exponential => interval.saturating_mul(1i64.checked_shl(times.min(62) as u32).unwrap_or(i64::MAX)),
+        };
+        if self.max_interval > 0 {
+            rtn = rtn.min(self.max_interval as i64);
+        }
+        // make sure the jitter won't overflow
+        rtn = rtn.min(i32::MAX as i64);
+        if self.jitter > 0 {
+            let range = rtn * self.jitter as i64 / 100;
+            rtn += random as i64 % (range * 2 + 1) - range;
+        }
+        rtn.max(0).min(i32::MAX as i64) as i32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serde_test() {
+        let policy = RetryPolicy::default();
+        assert_eq!(serde_json::to_string(&policy).unwrap(), "{}");
+        let policy: RetryPolicy = serde_json::from_str(r#"{"backoff":"linear","interval":3,"give_up":"drop"}"#).unwrap();
+        assert_eq!(policy.backoff, Backoff::Linear);
+        assert_eq!(policy.give_up, GiveUp::Drop);
+        assert_eq!(policy.max_attempts(6), 6);
+    }
+
+    #[test]
+    fn delay_test() {
+        let mut policy = RetryPolicy::default();
+        assert_eq!((0..5).map(|t| policy.delay(t, 5, 0)).collect::<Vec<i32>>(), vec![5, 10, 20, 40, 80]);
+        assert_eq!((0..5).map(|t| policy.delay(t, 3, 0)).collect::<Vec<i32>>(), vec![3, 6, 12, 24, 48]);
+        policy.max_interval = 30;
+        assert_eq!(policy.delay(4, 5, 0), 30);
+        assert_eq!(policy.delay(i16::MAX, 5, 0), 30);
+        policy.backoff = Backoff::Linear;
+        policy.interval = 7;
+        assert_eq!((0..3).map(|t| policy.delay(t, 5, 0)).collect::<Vec<i32>>(), vec![7, 14, 21]);
+        policy.backoff = Backoff::Fixed;
+        assert_eq!(policy.delay(10, 5, 0), 7);
+    }
+
+    #[test]
+    fn jitter_test() {
+        let mut policy = RetryPolicy {
+            backoff: Backoff::Fixed,
+            interval: 100,
+            jitter: 10,
+            ..Default::default()
+        };
+        for random in 0..100 {
+            let delay = policy.delay(0, 5, random);
+            assert!((90..=110).contains(&delay));
+        }
+        assert_eq!(policy.delay(0, 5, 10), 100);
+        // the exponential interval is saturated when retried too many times
+        policy.backoff = Backoff::Exponential;
+        policy.max_attempts = 100;
+        for times in 60..100 {
+            let delay = policy.delay(times, 5, u32::MAX);
+            assert!(delay > i32::MAX / 10 * 9);
+        }
+        policy.jitter = 101;
+        assert!(policy.verify().is_err());
+    }
+}
@@ -1,21 +1,11 @@
-use std::env;
+use crate::common::CONFIG;
 
 lazy_static! {
-    pub static ref SEPARATOR_INS_PARA:String={
-        env::var("SEPARATOR_INS_PARA").unwrap_or_else(|_| "/".to_string())
-    };
-    pub static ref SEPARATOR_INS_KEY:String={
-        env::var("SEPARATOR_INS_KEY").unwrap_or_else(|_| "|".to_string())
-    };
-    pub static ref SEPARATOR_TASK_KEY:String={
-        env::var("SEPARATOR_TASK_KEY").unwrap_or_else(|_| "|".to_string())
-    };
-    pub static ref SEPARATOR_META:String={
-        env::var("SEPARATOR_META").unwrap_or_else(|_| ":".to_string())
-    };
-    pub static ref SEPARATOR_META_KEY:String={
-        env::var("SEPARATOR_META_KEY").unwrap_or_else(|_| "/".to_string())
-    };
+    pub static ref SEPARATOR_INS_PARA: String = CONFIG.separator.ins_para.clone();
+    pub static ref SEPARATOR_INS_KEY: String = CONFIG.separator.ins_key.clone();
+    pub static ref SEPARATOR_TASK_KEY: String = CONFIG.separator.task_key.clone();
+    pub static ref SEPARATOR_META: String = CONFIG.separator.meta.clone();
+    pub static ref SEPARATOR_META_KEY: String = CONFIG.separator.meta_key.clone();
 }
 
 /// This is only used for deserialize
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use crate::common::{FromInstance, Instance, is_default};
+
+/// One version of a state-instance and what changed from the previous version
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct StateChange {
+    pub state_version: i32,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub states: HashSet<String>,
+    /// states which this version has but the previous version has not
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub added: Vec<String>,
+    /// states which the previous version has but this version has not
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub removed: Vec<String>,
+    /// the instance which caused this version
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub from: Option<FromInstance>,
+    pub create_time: i64,
+}
+
+impl StateChange {
+    /// `list` must be ordered by `state_version` ascending
+    pub fn timeline(list: &[Instance]) -> Vec<StateChange> {
+        let empty: HashSet<String> = HashSet::new();
+        let mut previous = &empty;
+        let mut rtn: Vec<StateChange> = vec![];
+        for one in list {
+            let mut added: Vec<String> = one.states.difference(previous).cloned().collect();
+            added.sort();
+            let mut removed: Vec<String> = previous.difference(&one.states).cloned().collect();
+            removed.sort();
+            rtn.push(StateChange {
+                state_version: one.state_version,
+                states: one.states.clone(),
+                added,
+                removed,
+                from: one.from.clone(),
+                create_time: one.create_time,
+            });
+            previous = &one.states;
+        }
+        rtn
+    }
+
+    /// at most `limit` changes after version `after`, `list` must be ordered by `state_version` ascending
+    /// and begin with the version `after` if it exists, so that the first change can be got.
+    pub fn page(list: &[Instance], after: i32, limit: usize) -> Vec<StateChange> {
+        let mut rtn = Self::timeline(list);
+        if after > 0 && rtn.first().map(|one| one.state_version) == Some(after) {
+            rtn.remove(0);
+        }
+        rtn.truncate(limit);
+        rtn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ins_with(version: i32, states: &[&str]) -> Instance {
+        let mut ins = Instance::default();
+        ins.state_version = version;
+        states.iter().for_each(|s| { ins.states.insert(s.to_string()); });
+        ins
+    }
+
+    #[test]
+    fn empty_test() {
+        assert_eq!(StateChange::timeline(&[]).len(), 0);
+    }
+
+    #[test]
+    fn page_test() {
+        let list = vec![ins_with(1, &["new"]), ins_with(2, &["paid", "new"]), ins_with(3, &["paid", "sent"])];
+        let rtn = StateChange::page(&list, 0, 2);
+        assert_eq!(rtn.len(), 2);
+        assert_eq!(rtn[1].state_version, 2);
+        // the changes of the first version in the page are based on the version before it
+        let rtn = StateChange::page(&list[1..], 2, 2);
+        assert_eq!(rtn.len(), 1);
+        assert_eq!(rtn[0].state_version, 3);
+        assert_eq!(rtn[0].added, vec!["sent".to_string()]);
+        assert_eq!(rtn[0].removed, vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn timeline_test() {
+        let list = vec![ins_with(1, &["new"]), ins_with(2, &["paid", "new"]), ins_with(3, &["paid", "sent"])];
+        let rtn = StateChange::timeline(&list);
+        assert_eq!(rtn.len(), 3);
+        assert_eq!(rtn[0].added, vec!["new".to_string()]);
+        assert_eq!(rtn[0].removed.len(), 0);
+        assert_eq!(rtn[1].added, vec!["paid".to_string()]);
+        assert_eq!(rtn[1].removed.len(), 0);
+        assert_eq!(rtn[2].state_version, 3);
+        assert_eq!(rtn[2].added, vec!["sent".to_string()]);
+        assert_eq!(rtn[2].removed, vec!["new".to_string()]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::common::{Instance, is_default, Meta, NatureError, Result};
+use crate::db::relation_target::RelationTarget;
+
+/// used for changing the states of a state-instance directly, not through a `Relation`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct StateUpdate {
+    pub meta: String,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub id: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub para: String,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub state_add: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub state_remove: Vec<String>,
+}
+
+impl StateUpdate {
+    pub fn verify(&self, meta: &Meta) -> Result<()> {
+        if !meta.is_state() {
+            return Err(NatureError::VerifyError(format!("[{}] is not a state meta", meta.meta_string())));
+        }
+        if self.id == 0 && self.para.is_empty() {
+            return Err(NatureError::VerifyError("id and para can not be empty at the same time".to_string()));
+        }
+        if self.state_add.is_empty() && self.state_remove.is_empty() {
+            return Err(NatureError::VerifyError("nothing to change for the states".to_string()));
+        }
+        // `check_state` will report undefined states
+        if !self.state_add.is_empty() {
+            meta.check_state(&self.state_add)?;
+        }
+        if let Some(s) = self.state_remove.iter().find(|s| !meta.has_state_name(s)) {
+            let msg = format!("[{}] does not defined in meta: {}", s, meta.meta_string());
+            return Err(NatureError::VerifyError(msg));
+        }
+        Ok(())
+    }
+
+    /// generate the next version based on the last one, `last` is `None` means the first version.
+    pub fn next_version(&self, last: Option<Instance>, meta: &Meta) -> Instance {
+        let mut rtn = match last {
+            Some(ins) => ins,
+            None => {
+                let mut ins = Instance::default();
+                ins.id = self.id;
+                ins.meta = meta.meta_string();
+                ins.para = self.para.to_string();
+                ins
+            }
+        };
+        rtn.from = None;
+        rtn.state_version += 1;
+        rtn.modify_state(&self.to_target(), meta);
+        rtn
+    }
+
+    fn to_target(&self) -> RelationTarget {
+        RelationTarget {
+            state_add: self.state_add.clone(),
+            state_remove: self.state_remove.clone(),
+            append_para: vec![],
+            dynamic_para: "".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::{MetaType, State};
+
+    use super::*;
+
+    fn state_meta() -> Meta {
+        let mut meta = Meta::new("order", 1, MetaType::Business).unwrap();
+        let (states, _) = State::string_to_states("new,paid|cancelled").unwrap();
+        let _ = meta.set_states(Some(states));
+        meta
+    }
+
+    fn update(add: &[&str], remove: &[&str]) -> StateUpdate {
+        StateUpdate {
+            meta: "B:order:1".to_string(),
+            id: 1,
+            para: "".to_string(),
+            state_add: add.iter().map(|s| s.to_string()).collect(),
+            state_remove: remove.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn verify_test() {
+        let meta = state_meta();
+        assert!(update(&["paid"], &[]).verify(&meta).is_ok());
+        assert!(update(&[], &["new"]).verify(&meta).is_ok());
+        assert!(update(&[], &[]).verify(&meta).is_err());
+        assert!(update(&["lost"], &[]).verify(&meta).is_err());
+        assert!(update(&[], &["lost"]).verify(&meta).is_err());
+        let mut no_key = update(&["paid"], &[]);
+        no_key.id = 0;
+        assert!(no_key.verify(&meta).is_err());
+        let normal = Meta::new("order", 1, MetaType::Business).unwrap();
+        assert!(update(&["paid"], &[]).verify(&normal).is_err());
+    }
+
+    #[test]
+    fn first_version_test() {
+        let meta = state_meta();
+        let rtn = update(&["new"], &[]).next_version(None, &meta);
+        assert_eq!(rtn.meta, "B:order:1");
+        assert_eq!(rtn.id, 1);
+        assert_eq!(rtn.state_version, 1);
+        assert!(rtn.states.contains("new"));
+    }
+
+    #[test]
+    fn mutex_and_remove_test() {
+        let meta = state_meta();
+        let mut last = update(&["new", "paid"], &[]).next_version(None, &meta);
+        last.content = "keep".to_string();
+        let rtn = update(&["cancelled"], &["new"]).next_version(Some(last), &meta);
+        assert_eq!(rtn.state_version, 2);
+        assert_eq!(rtn.content, "keep");
+        assert_eq!(rtn.states.len(), 1);
+        assert!(rtn.states.contains("cancelled"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Duration, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::common::{NatureError, Result};
+
+/// Time zone used to decide the boundaries of day, week, month and year, and to parse time strings.
+/// It can be an IANA name like "Asia/Shanghai" or a fixed offset like "+08:00", "UTC".
+/// Empty means the time zone of the server.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Zone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+    Iana(Tz),
+}
+
+impl Zone {
+    /// the wall clock time in this zone
+    pub fn to_naive(&self, millis: i64) -> NaiveDateTime {
+        match self {
+            Zone::Local => Local.timestamp_millis(millis).naive_local(),
+            Zone::Fixed(z) => z.timestamp_millis(millis).naive_local(),
+            Zone::Iana(z) => z.timestamp_millis(millis).naive_local(),
+        }
+    }
+
+    /// milliseconds of the wall clock time in this zone. For the time skipped by DST,
+    /// the first valid time after it will be used.
+    pub fn from_naive(&self, nd: &NaiveDateTime) -> Result<i64> {
+        // DST gap is not longer than 2 hours in practice
+        for minutes in (0..=120).step_by(15) {
+            let nd = *nd + Duration::minutes(minutes);
+            let rtn = match self {
+                Zone::Local => earliest(Local.from_local_datetime(&nd)),
+                Zone::Fixed(z) => earliest(z.from_local_datetime(&nd)),
+                Zone::Iana(z) => earliest(z.from_local_datetime(&nd)),
+            };
+            if let Some(rtn) = rtn {
+                return Ok(rtn);
+            }
+        }
+        Err(NatureError::VerifyError(format!("invalid time {} for time zone {}", nd, self)))
+    }
+
+    /// milliseconds of the beginning of the day in this zone
+    pub fn day_start(&self, date: &NaiveDate) -> Result<i64> {
+        self.from_naive(&date.and_hms(0, 0, 0))
+    }
+
+    /// offset from UTC in milliseconds at the given time
+    pub fn offset_millis(&self, millis: i64) -> i64 {
+        let seconds = match self {
+            Zone::Local => Local.timestamp_millis(millis).offset().fix().local_minus_utc(),
+            Zone::Fixed(z) => z.local_minus_utc(),
+            Zone::Iana(z) => z.timestamp_millis(millis).offset().fix().local_minus_utc(),
+        };
+        seconds as i64 * 1000
+    }
+
+    /// Parse the time to milliseconds. Integer will be treated as seconds from the epoch,
+    /// otherwise the format should be "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S" or "%Y-%m-%d"
+    /// in this zone.
+    pub fn parse_time(&self, time: &str) -> Result<i64> {
+        if let Ok(seconds) = i64::from_str(time) {
+            return Ok(seconds * 1000);
+        }
+        for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(nd) = NaiveDateTime::parse_from_str(time, format) {
+                return self.from_naive(&nd);
+            }
+        }
+        match NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+            Ok(date) => self.day_start(&date),
+            Err(_) => Err(NatureError::VerifyError(format!("unrecognized time: {}", time)))
+        }
+    }
+}
+
+fn earliest<T: TimeZone>(rtn: LocalResult<chrono::DateTime<T>>) -> Option<i64> {
+    match rtn {
+        LocalResult::Single(t) => Some(t.timestamp_millis()),
+        LocalResult::Ambiguous(t, _) => Some(t.timestamp_millis()),
+        LocalResult::None => None,
+    }
+}
+
+impl FromStr for Zone {
+    type Err = NatureError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(Zone::Local);
+        }
+        if s == "UTC" || s == "Z" {
+            return Ok(Zone::Fixed(FixedOffset::east(0)));
+        }
+        if s.starts_with('+') || s.starts_with('-') {
+            return parse_offset(s);
+        }
+        match Tz::from_str(s) {
+            Ok(tz) => Ok(Zone::Iana(tz)),
+            Err(e) => Err(NatureError::VerifyError(format!("unknown time zone {}: {}", s, e)))
+        }
+    }
+}
+
+/// parse "+08:00", "-0530" or "+8"
+fn parse_offset(s: &str) -> Result<Zone> {
+    let err = || NatureError::VerifyError(format!("illegal time zone offset: {}", s));
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let body = s[1..].replace(':', "");
+    let (hour, minute) = match body.len() {
+        1 | 2 => (i32::from_str(&body).map_err(|_| err())?, 0),
+        4 => (i32::from_str(&body[0..2]).map_err(|_| err())?, i32::from_str(&body[2..]).map_err(|_| err())?),
+        _ => return Err(err())
+    };
+    if hour > 14 || minute > 59 {
+        return Err(err());
+    }
+    Ok(Zone::Fixed(FixedOffset::east(sign * (hour * 3600 + minute * 60))))
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => write!(f, ""),
+            Zone::Fixed(z) => write!(f, "{}", z),
+            Zone::Iana(z) => write!(f, "{}", z.name()),
+        }
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        Zone::from_str(&s).map_err(|e| serde::de::Error::custom(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!(Zone::from_str("").unwrap(), Zone::Local);
+        assert_eq!(Zone::from_str("UTC").unwrap(), Zone::Fixed(FixedOffset::east(0)));
+        assert_eq!(Zone::from_str("+08:00").unwrap(), Zone::Fixed(FixedOffset::east(8 * 3600)));
+        assert_eq!(Zone::from_str("-0530").unwrap(), Zone::Fixed(FixedOffset::west(5 * 3600 + 30 * 60)));
+        assert_eq!(Zone::from_str("+8").unwrap(), Zone::Fixed(FixedOffset::east(8 * 3600)));
+        assert_eq!(Zone::from_str("Asia/Shanghai").unwrap(), Zone::Iana(Tz::Asia__Shanghai));
+        assert!(Zone::from_str("+25:00").is_err());
+        assert!(Zone::from_str("Mars/Base").is_err());
+    }
+
+    #[test]
+    fn serde_test() {
+        let zones: Vec<Zone> = serde_json::from_str(r#"["", "+08:00", "Europe/Berlin"]"#).unwrap();
+        assert_eq!(zones[2], Zone::Iana(Tz::Europe__Berlin));
+        let json = serde_json::to_string(&zones).unwrap();
+        assert_eq!(json, r#"["","+08:00","Europe/Berlin"]"#);
+    }
+
+    #[test]
+    fn dst_test() {
+        let zone = Zone::from_str("America/New_York").unwrap();
+        // 2020-03-08 02:30 does not exist in New York
+        let nd = NaiveDate::from_ymd(2020, 3, 8).and_hms(2, 30, 0);
+        let rtn = zone.from_naive(&nd).unwrap();
+        assert_eq!(zone.to_naive(rtn), NaiveDate::from_ymd(2020, 3, 8).and_hms(3, 0, 0));
+        // offset changed between days
+        let before = zone.day_start(&NaiveDate::from_ymd(2020, 3, 8)).unwrap();
+        let after = zone.day_start(&NaiveDate::from_ymd(2020, 3, 9)).unwrap();
+        assert_eq!(after - before, 23 * 3600 * 1000);
+        assert_eq!(zone.offset_millis(before), -5 * 3600 * 1000);
+        assert_eq!(zone.offset_millis(after), -4 * 3600 * 1000);
+    }
+
+    #[test]
+    fn parse_time_test() {
+        let zone = Zone::from_str("+08:00").unwrap();
+        assert_eq!(zone.parse_time("1596207504").unwrap(), 1596207504000);
+        assert_eq!(zone.parse_time("2020-08-01").unwrap(), 1596211200000);
+        assert_eq!(zone.parse_time("2020-08-01 08:00:00").unwrap(), 1596240000000);
+        assert_eq!(zone.parse_time("2020-08-01T08:00:00").unwrap(), 1596240000000);
+        assert!(zone.parse_time("yesterday").is_err());
+    }
+}
@@ -1,13 +1,19 @@
 pub use act_batch::*;
 pub use act_convert::*;
+pub use act_dead_letter::*;
 pub use act_store::*;
 pub use act_stored::*;
 pub use after_converted::*;
+pub use health_controller::*;
 pub use income_controller::*;
+pub use loop_controller::*;
 
 mod act_convert;
 mod act_batch;
+mod act_dead_letter;
 mod act_store;
 mod act_stored;
 mod after_converted;
+mod health_controller;
 mod income_controller;
+mod loop_controller;
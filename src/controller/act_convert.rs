@@ -31,6 +31,10 @@ pub(crate) async fn do_convert(task: TaskForConvert, raw: RawTask) {
     // -----end
     let mut task = task;
     let last = match InstanceDaoImpl::get_last_target(&from_instance, &mut task.target).await {
+        Err(e @ NatureError::LogicalError(_)) => {
+            let _ = D_T.raw_to_error(&e, &raw).await;
+            return;
+        }
         Err(_) => { return; }
         Ok(last) => last
     };
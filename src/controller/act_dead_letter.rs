@@ -0,0 +1,108 @@
+use std::future::Future;
+
+use reqwest::Client;
+use tokio::runtime::Runtime;
+
+use crate::common::{CONFIG, DeadLetter, Instance, Result};
+use crate::controller::IncomeController;
+
+lazy_static! {
+    static ref CLIENT : Client = Client::new();
+}
+
+/// used by the server, the instance is input in-process
+pub fn channel_dead_letter(letter: DeadLetter) {
+    handle_dead_letter(letter, IncomeController::input)
+}
+
+/// `input` is used to input the instance of the dead letter meta,
+/// the `retry` program gives one which sends it to a Nature node.
+pub fn handle_dead_letter<F, R>(letter: DeadLetter, input: F)
+    where F: Fn(Instance) -> R,
+          R: Future<Output=Result<u64>>
+{
+    let mut runtime = match Runtime::new() {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("get tokio runtime error : {}", e);
+            return;
+        }
+    };
+    runtime.block_on(notify(letter, input));
+}
+
+async fn notify<F, R>(letter: DeadLetter, input: F)
+    where F: Fn(Instance) -> R,
+          R: Future<Output=Result<u64>>
+{
+    let cfg = &CONFIG.dead_letter;
+    if let Some(webhook) = &cfg.webhook {
+        if let Err(e) = post(webhook, &letter).await {
+            warn!("dead letter webhook failed for task: {}, err: {}", letter.task_id, e);
+        }
+    }
+    if let Some(meta) = &cfg.meta {
+        // the failure of the dead letter itself should not generate another one
+        if is_dead_letter(&letter, meta) {
+            warn!("dead letter instance failed, task: {}", letter.task_key);
+            return;
+        }
+        let rtn = match to_instance(&letter, meta) {
+            Ok(ins) => input(ins).await,
+            Err(e) => Err(e)
+        };
+        if let Err(e) = rtn {
+            warn!("input dead letter instance failed for task: {}, err: {}", letter.task_id, e);
+        }
+    }
+}
+
+async fn post(webhook: &str, letter: &DeadLetter) -> Result<()> {
+    let _ = CLIENT.post(webhook).json(letter).send().await?.error_for_status()?;
+    Ok(())
+}
+
+fn is_dead_letter(letter: &DeadLetter, meta: &str) -> bool {
+    letter.task_key.starts_with(meta) || letter.task_for == meta
+}
+
+fn to_instance(letter: &DeadLetter, meta: &str) -> Result<Instance> {
+    let mut ins = Instance::default();
+    ins.meta = meta.to_string();
+    ins.content = serde_json::to_string(letter)?;
+    Ok(ins)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Local;
+    use serde_json::Value;
+
+    use super::*;
+
+    fn letter(task_key: &str) -> DeadLetter {
+        DeadLetter {
+            task_id: 1,
+            task_key: task_key.to_string(),
+            task_type: 2,
+            task_for: "B:sale/total:1".to_string(),
+            msg: "LogicalError(\"bad\")".to_string(),
+            data: Value::Null,
+            create_time: Local::now().naive_local(),
+        }
+    }
+
+    #[test]
+    fn to_instance_test() {
+        let letter = letter("B:sale/order:1|5|");
+        let ins = to_instance(&letter, "S:task/error:1").unwrap();
+        assert_eq!(ins.meta, "S:task/error:1");
+        assert_eq!(serde_json::from_str::<DeadLetter>(&ins.content).unwrap(), letter);
+    }
+
+    #[test]
+    fn is_dead_letter_test() {
+        assert!(!is_dead_letter(&letter("B:sale/order:1|5|"), "S:task/error:1"));
+        assert!(is_dead_letter(&letter("S:task/error:1|5|"), "S:task/error:1"));
+    }
+}
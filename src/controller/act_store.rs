@@ -10,6 +10,14 @@ use crate::task::{CachedKey, TaskForConvert, TaskForStore};
 use crate::task::gen_loop_mission;
 
 pub async fn channel_store(task: TaskForStore, carrier: RawTask) -> Result<()> {
+    match save_instance(task, carrier).await? {
+        None => Ok(()),
+        Some((task, carrier)) => duplicated_instance(task, carrier).await
+    }
+}
+
+/// save the instance and do the things after saved, the task will be given back if the instance is duplicated.
+pub async fn save_instance(task: TaskForStore, carrier: RawTask) -> Result<Option<(TaskForStore, RawTask)>> {
     match InstanceDaoImpl::insert(&task.instance).await {
         Ok(_) => {
             // debug!("saved instance for: {}, task for: {:?}", &task.instance.meta, &task.next_mission);
@@ -18,11 +26,9 @@ pub async fn channel_store(task: TaskForStore, carrier: RawTask) -> Result<()> {
             //     after_saved(task, carrier).await
             // });
             let _ = after_saved(task, carrier).await;
-            Ok(())
-        }
-        Err(NatureError::DaoDuplicated(_)) => {
-            duplicated_instance(task, carrier).await
+            Ok(None)
         }
+        Err(NatureError::DaoDuplicated(_)) => Ok(Some((task, carrier))),
         Err(e) => Err(e)
     }
 }
@@ -66,24 +72,23 @@ async fn duplicated_instance(task: TaskForStore, carrier: RawTask) -> Result<()>
         sleep(Duration::from_millis(10));
         let mut rtn = TaskForConvert::from_raw(&carrier, InstanceDaoImpl::get_by_id, &*C_M, &*D_M).await?;
         rtn.conflict_version = task.instance.state_version;
-        CHANNEL_CONVERT.sender.lock().unwrap().send((rtn, carrier))?;
+        CHANNEL_CONVERT.send((rtn, carrier))?;
         Ok(())
     }
 }
 
 pub async fn get_store_task(instance: &Instance, previous_mission: Option<Mission>) -> Result<TaskForStore> {
-    let meta_type = C_M.get(&instance.meta, &*D_M).await?.get_meta_type();
+    let meta = C_M.get(&instance.meta, &*D_M).await?;
 
-    let mission = match meta_type {
+    let mission = match meta.get_meta_type() {
         MetaType::Loop => {
             gen_loop_mission(instance, &*C_M, &*D_M).await?
         }
         _ => {
             let relations = C_R.get(&instance.meta, &*D_R, &*C_M, &*D_M).await?;
-            Mission::get_by_instance(instance, &relations, context_check, state_check)
+            Mission::get_by_instance(instance, &meta, &relations, context_check, state_check)
         }
     };
-    let meta = C_M.get(&instance.meta, &*D_M).await?;
     let task = TaskForStore::new(instance.clone(), mission, previous_mission, meta.need_cache());
     Ok(task)
 }
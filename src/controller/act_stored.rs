@@ -22,7 +22,7 @@ pub async fn channel_stored(task: TaskForStore, raw: RawTask) {
                 for t in converters {
                     if t.0.target.delay == 0 {
                         // do_convert(t.0,t.1).await
-                        let _ = CHANNEL_CONVERT.sender.lock().unwrap().send(t);
+                        let _ = CHANNEL_CONVERT.send(t);
                     }
                 }
             });
@@ -0,0 +1,58 @@
+use crate::channels::CHANNEL_CONVERT;
+use crate::common::{CONFIG, Readiness};
+use crate::db::MySql;
+
+lazy_static! {
+    /// the node is not ready when there are more tasks than this waiting in the convert queue
+    pub static ref READY_QUEUE_LIMIT : usize = CONFIG.server.ready_queue_limit;
+}
+
+pub struct HealthController {}
+
+impl HealthController {
+    /// whether the node can accept requests
+    pub async fn ready() -> Readiness {
+        let mut rtn = Readiness {
+            ready: false,
+            db: true,
+            workers: CHANNEL_CONVERT.running(),
+            queue_depth: CHANNEL_CONVERT.depth(),
+            problems: vec![],
+        };
+        if let Err(e) = MySql::ping().await {
+            rtn.db = false;
+            rtn.problems.push(format!("database unreachable: {}", e));
+        }
+        check(&mut rtn, *READY_QUEUE_LIMIT);
+        rtn
+    }
+}
+
+fn check(rtn: &mut Readiness, queue_limit: usize) {
+    if rtn.workers == 0 {
+        rtn.problems.push("no convert thread running".to_string());
+    }
+    if rtn.queue_depth > queue_limit {
+        rtn.problems.push(format!("too many tasks in the convert queue: {}", rtn.queue_depth));
+    }
+    rtn.ready = rtn.problems.is_empty();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_test() {
+        let mut rtn = Readiness { ready: false, db: true, workers: 1, queue_depth: 10, problems: vec![] };
+        check(&mut rtn, 10);
+        assert!(rtn.ready);
+        rtn.queue_depth = 11;
+        check(&mut rtn, 10);
+        assert!(!rtn.ready);
+        assert_eq!(rtn.problems.len(), 1);
+        let mut rtn = Readiness { ready: false, db: true, workers: 0, queue_depth: 0, problems: vec![] };
+        check(&mut rtn, 10);
+        assert!(!rtn.ready);
+    }
+}
@@ -1,10 +1,13 @@
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::time::Duration;
 
 use crate::channels::CHANNEL_CONVERT;
-use crate::common::{ConverterReturned, DelayedInstances, generate_id, Instance, KeyCondition, Meta, MetaType, NatureError, Result, SelfRouteInstance};
+use crate::common::{ConverterReturned, DelayedInstances, generate_id, Instance, KeyCondition, Meta, MetaType, NatureError, Result, SelfRouteInstance, StateUpdate};
 use crate::controller::*;
 use crate::db::{C_M, C_R, D_M, D_R, D_T, InstanceDaoImpl, MetaCache, Mission, RawTask, RelationCache, TaskDao, TaskType};
 use crate::db::flow_tool::{context_check, state_check};
+use crate::system::STATE_UPDATE_RETRY_TIMES;
 use crate::task::{TaskForConvert, TaskForStore};
 
 pub struct IncomeController {}
@@ -12,9 +15,9 @@ pub struct IncomeController {}
 impl IncomeController {
     /// born an instance which is the beginning of the changes.
     pub async fn input(mut instance: Instance) -> Result<u64> {
-        let _ = check_and_revise(&mut instance).await?;
+        let meta = check_and_revise(&mut instance).await?;
         let relations = C_R.get(&instance.meta, &*D_R, &*C_M, &*D_M).await?;
-        let mission = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let mission = Mission::get_by_instance(&instance, &meta, &relations, context_check, state_check);
         // for o in &mission {
         //     debug!("--generate mission from:{},to:{}", &instance.meta, o.to.meta_string());
         // }
@@ -95,7 +98,7 @@ impl IncomeController {
             TaskType::Convert => {
                 let rtn = TaskForConvert::from_raw(&raw, InstanceDaoImpl::get_by_id, &*C_M, &*D_M).await?;
                 debug!("--redo convert task: from:{}, to:{}", rtn.from.meta, rtn.target.to.meta_string());
-                CHANNEL_CONVERT.sender.lock().unwrap().send((rtn, raw))?;
+                CHANNEL_CONVERT.send((rtn, raw))?;
             }
             TaskType::Batch => {
                 let rtn = serde_json::from_str(&raw.data)?;
@@ -106,6 +109,47 @@ impl IncomeController {
         Ok(())
     }
 
+    /// change states based on the last version, retry when other one saved the same version first.
+    /// return the new `state_version`
+    pub async fn update_state(update: StateUpdate) -> Result<i32> {
+        let meta: Meta = C_M.get(&update.meta, &*D_M).await?;
+        update.verify(&meta)?;
+        let kc = KeyCondition::new(update.id, &meta.meta_string(), &update.para, 0);
+        let mut times = 0;
+        loop {
+            let last = InstanceDaoImpl::get_last_state(&kc).await?;
+            let last_states = match &last {
+                Some(ins) => ins.states.clone(),
+                None => HashSet::new()
+            };
+            meta.check_terminal(&last_states)?;
+            let mut instance = update.next_version(last, &meta);
+            meta.check_transition(&last_states, &instance.states)?;
+            instance.revise()?;
+            let task = get_store_task(&instance, None).await?;
+            let mut raw = task.to_raw()?;
+            let num = D_T.insert(&raw).await?;
+            if num > 0 {
+                raw.task_id = num;
+                match save_instance(task, raw).await? {
+                    None => return Ok(instance.state_version),
+                    Some((_, raw)) => {
+                        let _ = D_T.delete(&raw.task_id).await?;
+                    }
+                }
+            }
+            // the same version had been saved by others
+            times += 1;
+            if times > *STATE_UPDATE_RETRY_TIMES {
+                let msg = format!("conflict for state-meta: [{}] retried {} times", instance.key_no_state(), times - 1);
+                warn!("{}", msg);
+                return Err(NatureError::LogicalError(msg));
+            }
+            warn!("conflict for state-meta: [{}] on version : {}", &instance.meta, instance.state_version);
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+    }
+
     pub async fn batch(batch: Vec<Instance>) -> Result<()> {
         let id = generate_id(&batch)?;
         let mut raw = RawTask::new(&batch, &id.to_string(), TaskType::Batch as i8, &batch[0].meta)?;
@@ -125,7 +169,8 @@ async fn get_task_and_last(task: &RawTask) -> Result<(TaskForConvert, Option<Ins
     Ok((task, last))
 }
 
-async fn check_and_revise(instance: &mut Instance) -> Result<&mut Instance> {
+/// return the `Meta` of the instance
+async fn check_and_revise(instance: &mut Instance) -> Result<Meta> {
     let meta: Meta = C_M.get(&instance.meta, &*D_M).await?;    // verify meta
     // normalize meta
     instance.meta = meta.meta_string();
@@ -135,9 +180,16 @@ async fn check_and_revise(instance: &mut Instance) -> Result<&mut Instance> {
         let mut kc: KeyCondition = instance.clone().into();
         kc.state_version = version - 1;
         let rtn = InstanceDaoImpl::get_by_id(kc).await?;
-        if rtn.is_none() {
-            return Err(NatureError::VerifyError("you can't skip state_version for instance".to_string()));
+        match rtn {
+            None => return Err(NatureError::VerifyError("you can't skip state_version for instance".to_string())),
+            Some(last) => {
+                meta.check_terminal(&last.states)?;
+                meta.check_transition(&last.states, &instance.states)?
+            }
         }
+    } else if meta.is_state() {
+        meta.check_transition(&HashSet::new(), &instance.states)?;
     }
-    instance.revise()
+    instance.revise()?;
+    Ok(meta)
 }
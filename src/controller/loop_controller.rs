@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+use crate::common::{CONTEXT_LOOP_FINISHED, CONTEXT_LOOP_ID, CONTEXT_LOOP_NEXT, LoopProgress, LoopResume, MetaType, NatureError, Result, SEPARATOR_META};
+use crate::db::{C_M, D_M, D_T, InstanceDaoImpl, MissionRaw, RawTask, TaskDao, TaskType};
+use crate::task::TaskForConvert;
+
+/// show and control the progress of the `MetaType::Loop`
+pub struct LoopController {}
+
+impl LoopController {
+    /// list the loops which are not finished
+    pub async fn list(limit: i64) -> Result<Vec<LoopProgress>> {
+        let tasks = D_T.get_loops(limit).await?;
+        let mut rtn: Vec<LoopProgress> = Vec::with_capacity(tasks.len());
+        for raw in tasks {
+            let task = match TaskForConvert::from_raw(&raw, InstanceDaoImpl::get_by_id, &*C_M, &*D_M).await {
+                Ok(task) => task,
+                Err(e) => {
+                    warn!("can't decode loop task: {}, err: {}", raw.task_id, e);
+                    continue;
+                }
+            };
+            let iterations = match task.from.sys_context.get(CONTEXT_LOOP_ID) {
+                Some(id) => u32::from_str(id)?,
+                None => 0
+            };
+            rtn.push(LoopProgress {
+                task_id: raw.task_id,
+                task_key: raw.task_key,
+                task_for: raw.task_for,
+                next: task.from.sys_context.get(CONTEXT_LOOP_NEXT).cloned(),
+                iterations,
+                retried_times: raw.retried_times,
+                create_time: raw.create_time,
+                execute_time: raw.execute_time,
+            });
+        }
+        Ok(rtn)
+    }
+
+    /// stop the loop, the task will be moved to the error table
+    pub async fn cancel(task_id: u64) -> Result<()> {
+        let raw = get_loop_task(task_id).await?;
+        let err = NatureError::LogicalError("loop canceled".to_string());
+        let _ = D_T.raw_to_error(&err, &raw).await?;
+        warn!("loop canceled for task: {}", raw.task_key);
+        Ok(())
+    }
+
+    /// save the given key to the task, and the retry program will execute the next iteration from it soon.
+    /// the task is not executed here, so that only one copy of it runs.
+    pub async fn resume(resume: LoopResume) -> Result<()> {
+        let mut raw = get_loop_task(resume.task_id).await?;
+        let mut mission = MissionRaw::from_json(&raw.data)?;
+        mission.sys_context.insert(CONTEXT_LOOP_NEXT.to_string(), resume.next.to_string());
+        mission.sys_context.remove(CONTEXT_LOOP_FINISHED);
+        raw.data = mission.to_json()?;
+        if D_T.redo_with_data(&raw).await? == 0 {
+            return Err(NatureError::VerifyError(format!("loop task: {} is running or finished, please try again later", raw.task_id)));
+        }
+        debug!("--resume loop task: {} from: {}", raw.task_key, resume.next);
+        Ok(())
+    }
+}
+
+async fn get_loop_task(task_id: u64) -> Result<RawTask> {
+    let raw = match D_T.get(&task_id).await? {
+        None => return Err(NatureError::VerifyError(format!("can't find task: {}, maybe it had done already", task_id))),
+        Some(raw) => raw
+    };
+    check_loop_task(&raw)?;
+    Ok(raw)
+}
+
+fn check_loop_task(raw: &RawTask) -> Result<()> {
+    let prefix = format!("{}{}", MetaType::Loop.get_prefix(), *SEPARATOR_META);
+    if raw.task_type != TaskType::Convert as i8 || !raw.task_for.starts_with(&prefix) {
+        return Err(NatureError::VerifyError(format!("task: {} is not a loop task", raw.task_id)));
+    }
+    if raw.task_state != 0 {
+        return Err(NatureError::VerifyError(format!("loop task: {} had finished", raw.task_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_loop_task_test() {
+        let mut raw = RawTask {
+            task_type: TaskType::Convert as i8,
+            task_for: "L:sale/total:1".to_string(),
+            ..Default::default()
+        };
+        assert!(check_loop_task(&raw).is_ok());
+        raw.task_state = 1;
+        assert!(check_loop_task(&raw).is_err());
+        raw.task_state = 0;
+        raw.task_for = "B:sale/total:1".to_string();
+        assert!(check_loop_task(&raw).is_err());
+        raw.task_for = "L:sale/total:1".to_string();
+        raw.task_type = TaskType::Store as i8;
+        assert!(check_loop_task(&raw).is_err());
+    }
+}
@@ -329,6 +329,9 @@ mod test {
             multi_meta: Default::default(),
             cache_saved: false,
             only_one: false,
+            transitions: Default::default(),
+            terminal_states: Default::default(),
+            retry: None,
         };
         let mut m = Meta::from_string("B:test:3").unwrap();
         let _ = m.set_setting(&setting.to_json().unwrap());
@@ -353,6 +356,9 @@ mod test {
             multi_meta: set,
             cache_saved: false,
             only_one: false,
+            transitions: Default::default(),
+            terminal_states: Default::default(),
+            retry: None,
         };
         let mut m = Meta::from_string("B:test:3").unwrap();
         let _ = m.set_setting(&setting.to_json().unwrap());
@@ -392,6 +398,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "sub-has-state".to_string();
@@ -408,6 +417,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "one-state".to_string();
@@ -425,6 +437,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "multi".to_string();
@@ -454,6 +469,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "one-state".to_string();
@@ -471,6 +489,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "sub".to_string();
@@ -492,6 +513,9 @@ mod test {
                         multi_meta: set,
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "sub-2".to_string();
@@ -517,6 +541,9 @@ mod test {
                         multi_meta: Default::default(),
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "child".to_string();
@@ -530,6 +557,9 @@ mod test {
                         multi_meta: Default::default(),
                         cache_saved: false,
                         only_one: false,
+                        transitions: Default::default(),
+                        terminal_states: Default::default(),
+                        retry: None,
                     };
                     let mut rtn = RawMeta::default();
                     rtn.meta_key = "master".to_string();
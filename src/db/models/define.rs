@@ -1,18 +1,8 @@
-use std::env;
+use crate::common::CONFIG;
 
 lazy_static! {
-    pub static ref INSTANCE_CONTENT_MAX_LENGTH : usize = {
-        env::var("INSTANCE_CONTENT_MAX_LENGTH").unwrap_or_else(|_| "65535".to_string()).parse::<usize>().unwrap()
-    };
-    pub static ref INSTANCE_CONTEXT_MAX_LENGTH : usize = {
-        env::var("INSTANCE_CONTEXT_MAX_LENGTH").unwrap_or_else(|_| "65535".to_string()).parse::<usize>().unwrap()
-    };
-    pub static ref TASK_CONTENT_MAX_LENGTH : usize = {
-        env::var("TASKY_CONTENT_MAX_LENGTH").unwrap_or_else(|_| "16777215".to_string()).parse::<usize>().unwrap()
-    };
-
-    pub static ref QUERY_SIZE_LIMIT : i32 = {
-        env::var("QUERY_SIZE_LIMIT").unwrap_or_else(|_| "1000".to_string()).parse::<i32>().unwrap()
-    };
-
+    pub static ref INSTANCE_CONTENT_MAX_LENGTH : usize = CONFIG.db.instance_content_max_length;
+    pub static ref INSTANCE_CONTEXT_MAX_LENGTH : usize = CONFIG.db.instance_context_max_length;
+    pub static ref TASK_CONTENT_MAX_LENGTH : usize = CONFIG.db.task_content_max_length;
+    pub static ref QUERY_SIZE_LIMIT : i32 = CONFIG.db.query_size_limit;
 }
@@ -3,7 +3,7 @@ use std::ops::Sub;
 
 use chrono::{Local, TimeZone};
 
-use crate::common::{CONTEXT_DYNAMIC_PARA, DynamicConverter, Executor, get_para_and_key_from_para, Instance, is_default, Meta, MetaType, Result};
+use crate::common::{CONTEXT_DYNAMIC_PARA, DynamicConverter, Executor, get_para_and_key_from_para, Instance, is_default, Meta, MetaType, Result, RetryPolicy};
 use crate::db::{LastSelector, MetaCache, MetaDao, Relation};
 use crate::db::flow_tool::{ContextChecker, StateChecker};
 use crate::db::models::relation_target::RelationTarget;
@@ -20,6 +20,9 @@ pub struct Mission {
     pub delay: i32,
     pub sys_context: HashMap<String, String>,
     pub id_bridge: bool,
+    pub retry: RetryPolicy,
+    /// the executor is a `time_range` session window
+    pub session: bool,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -50,6 +53,13 @@ pub struct MissionRaw {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     pub id_bridge: bool,
+    /// persisted with the task, so the `retry` program can use it
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub session: bool,
 }
 
 impl From<Mission> for MissionRaw {
@@ -65,6 +75,8 @@ impl From<Mission> for MissionRaw {
             delay: input.delay,
             sys_context: input.sys_context,
             id_bridge: input.id_bridge,
+            retry: input.retry,
+            session: input.session,
         }
     }
 }
@@ -102,6 +114,8 @@ impl Mission {
                 delay: d.delay,
                 sys_context: Default::default(),
                 id_bridge: false,
+                retry: Default::default(),
+                session: false,
             };
             missions.push(mission)
         }
@@ -110,10 +124,20 @@ impl Mission {
     }
 
     /// Check the instance's context, sys_context and states whether satisfy the Selector request
-    pub fn get_by_instance(instance: &Instance, relations: &Vec<Relation>, ctx_chk: ContextChecker, sta_chk: StateChecker) -> Vec<Mission> {
+    /// `meta` is the instance's `Meta`, used to check whether the instance is in a terminal state.
+    ///
+    /// **Note**: a terminal instance only fires the relations marked `after_terminal`, and this
+    /// includes the version which just entered the terminal state, e.g. a relation reacting to
+    /// `cancelled` must be marked `after_terminal` if `cancelled` is terminal.
+    pub fn get_by_instance(instance: &Instance, meta: &Meta, relations: &Vec<Relation>, ctx_chk: ContextChecker, sta_chk: StateChecker) -> Vec<Mission> {
         if relations.is_empty() { return vec![]; }
+        let terminal = meta.is_terminal(&instance.states);
         let mut rtn: Vec<Mission> = Vec::new();
         for r in relations {
+            if terminal && !r.after_terminal {
+                debug!("instance {} is in terminal state, relation ignored: {}", instance.get_key(), r.relation_string());
+                continue;
+            }
             if r.selector.is_some() {
                 let selector = &r.selector.clone().unwrap();
                 if !ctx_chk(&instance.data.context, &selector.context_none, &selector.context_all, &selector.context_any) {
@@ -152,6 +176,8 @@ impl Mission {
             delay: raw.delay,
             sys_context: raw.sys_context.clone(),
             id_bridge: raw.id_bridge,
+            retry: raw.retry.clone(),
+            session: raw.session,
         };
         Ok(rtn)
     }
@@ -193,6 +219,10 @@ impl From<Relation> for Mission {
                 last_none: sel.last_none,
             }
         };
+        let retry = match r.retry {
+            Some(retry) => retry,
+            None => r.to.get_setting().and_then(|s| s.retry).unwrap_or_default()
+        };
         Mission {
             to: r.to.clone(),
             last_select,
@@ -204,6 +234,8 @@ impl From<Relation> for Mission {
             delay: 0,
             sys_context: Default::default(),
             id_bridge: r.id_bridge,
+            retry,
+            session: r.session,
         }
     }
 }
@@ -223,6 +255,7 @@ fn get_delay(ins: &Instance, rela: &Relation) -> Result<i32> {
 
 #[cfg(test)]
 mod test {
+    use crate::common::State;
     use crate::db::flow_tool::{context_check, state_check};
     use crate::db::FlowSelector;
     use crate::db::models::relation_target::RelationTarget;
@@ -269,10 +302,10 @@ mod test {
         relation.selector = Some(selector);
         let relations = vec![relation];
         let mut instance = Instance::default();
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), true);
         instance.states.insert("a".to_string());
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), false);
     }
 
@@ -284,10 +317,10 @@ mod test {
         relation.selector = Some(selector);
         let relations = vec![relation];
         let mut instance = Instance::default();
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), true);
         instance.sys_context.insert("a".to_string(), "x".to_string());
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), false);
     }
 
@@ -299,10 +332,10 @@ mod test {
         relation.selector = Some(selector);
         let relations = vec![relation];
         let mut instance = Instance::default();
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), true);
         instance.context.insert("a".to_string(), "x".to_string());
-        let rtn = Mission::get_by_instance(&instance, &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&instance, &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.is_empty(), false);
     }
 
@@ -325,7 +358,7 @@ mod test {
         relation.target = target;
         relation.delay = 2;
         let relations = vec![relation];
-        let rtn = Mission::get_by_instance(&Instance::default(), &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&Instance::default(), &Meta::default(), &relations, context_check, state_check);
         let rtn = &rtn[0];
         assert_eq!(rtn.delay, 2);
         assert_eq!(rtn.executor, executor);
@@ -337,20 +370,61 @@ mod test {
     #[test]
     fn many_relations() {
         let relations = vec![Relation::default(), Relation::default(), Relation::default()];
-        let rtn = Mission::get_by_instance(&Instance::default(), &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&Instance::default(), &Meta::default(), &relations, context_check, state_check);
         assert_eq!(rtn.len(), 3);
     }
 
     #[test]
     fn one_relation_but_no_selector() {
         let relations = vec![Relation::default()];
-        let rtn = Mission::get_by_instance(&Instance::default(), &relations, context_check, state_check);
+        let rtn = Mission::get_by_instance(&Instance::default(), &Meta::default(), &relations, context_check, state_check);
+        assert_eq!(rtn.len(), 1);
+    }
+
+    #[test]
+    fn terminal_state() {
+        let mut meta = Meta::from_string("B:order:1").unwrap();
+        let (ss, _) = State::string_to_states("new,closed").unwrap();
+        let _ = meta.set_states(Some(ss));
+        let _ = meta.set_setting(r#"{"terminal_states":["closed"]}"#);
+        let opt_in = Relation {
+            after_terminal: true,
+            ..Default::default()
+        };
+        let relations = vec![Relation::default(), opt_in];
+        let mut instance = Instance::default();
+        instance.states.insert("new".to_string());
+        let rtn = Mission::get_by_instance(&instance, &meta, &relations, context_check, state_check);
+        assert_eq!(rtn.len(), 2);
+        instance.states.insert("closed".to_string());
+        let rtn = Mission::get_by_instance(&instance, &meta, &relations, context_check, state_check);
         assert_eq!(rtn.len(), 1);
     }
 
+    #[test]
+    fn retry_policy() {
+        let mut meta = Meta::from_string("B:hello:1").unwrap();
+        let _ = meta.set_setting(r#"{"retry":{"interval":3}}"#);
+        let mut relation = Relation {
+            to: meta,
+            ..Default::default()
+        };
+        let rtn = Mission::from(relation.clone());
+        assert_eq!(rtn.retry.interval, 3);
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        relation.retry = Some(policy.clone());
+        let rtn = Mission::from(relation);
+        assert_eq!(rtn.retry, policy);
+        let raw = MissionRaw::from_json(&MissionRaw::from(rtn).to_json().unwrap()).unwrap();
+        assert_eq!(raw.retry, policy);
+    }
+
     #[test]
     fn no_relation() {
-        let rtn = Mission::get_by_instance(&Instance::default(), &vec![], context_check, state_check);
+        let rtn = Mission::get_by_instance(&Instance::default(), &Meta::default(), &vec![], context_check, state_check);
         assert_eq!(rtn.is_empty(), true);
     }
 }
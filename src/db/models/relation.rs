@@ -1,7 +1,8 @@
 use std::clone::Clone;
 use std::string::ToString;
 
-use crate::common::{Executor, Meta, NatureError, Protocol, Result};
+use crate::builtin_converter::BuiltIn;
+use crate::common::{Executor, Meta, NatureError, Protocol, Result, RetryPolicy};
 use crate::db::{FlowSelector, MetaCache, MetaDao, RawRelation, RelationSettings};
 use crate::db::models::relation_target::RelationTarget;
 
@@ -18,6 +19,10 @@ pub struct Relation {
     pub delay: i32,
     pub delay_on_pare: (i32, u8),
     pub id_bridge: bool,
+    pub after_terminal: bool,
+    pub retry: Option<RetryPolicy>,
+    /// the executor is a `time_range` session window, resolved when loaded
+    pub session: bool,
 }
 
 impl Iterator for Relation {
@@ -39,6 +44,13 @@ impl Relation {
                 return Err(NatureError::VerifyError(msg));
             }
         };
+        if let Some(retry) = &settings.retry {
+            if let Err(e) = retry.verify() {
+                let msg = format!("{}'s retry setting error: {}", val.get_string(), e);
+                warn!("{}", &msg);
+                return Err(NatureError::VerifyError(msg));
+            }
+        }
         let selector = &settings.selector;
         let m_to = Relation::check_converter(&val.to_meta, meta_cache_getter, meta_getter, &settings).await?;
         let rtn = match settings.executor {
@@ -48,6 +60,12 @@ impl Relation {
                     let err = format!("{} Protocol::Auto can not be used by user. ", val.get_string());
                     return Err(NatureError::VerifyError(err));
                 }
+                // the session is kept in the state of every key
+                let session = BuiltIn::is_session(&e);
+                if session && (!m_to.is_state() || settings.target.append_para.is_empty()) {
+                    let err = format!("{} session window needs a state target with `append_para`. ", val.get_string());
+                    return Err(NatureError::VerifyError(err));
+                }
                 Relation {
                     from: val.from_meta.to_string(),
                     to: m_to,
@@ -60,6 +78,9 @@ impl Relation {
                     delay: settings.delay,
                     delay_on_pare: settings.delay_on_para,
                     id_bridge: settings.id_bridge,
+                    after_terminal: settings.after_terminal,
+                    retry: settings.retry.clone(),
+                    session,
                 }
             }
             None => Relation {
@@ -74,6 +95,9 @@ impl Relation {
                 delay: settings.delay,
                 delay_on_pare: settings.delay_on_para,
                 id_bridge: settings.id_bridge,
+                after_terminal: settings.after_terminal,
+                retry: settings.retry.clone(),
+                session: false,
             }
         };
         debug!("load {}", val.get_string());
@@ -111,7 +135,7 @@ impl Relation {
 mod test_from_raw {
     use tokio::runtime::Runtime;
 
-    use crate::db::RawMeta;
+    use crate::db::{Mission, RawMeta};
 
     use super::*;
 
@@ -159,6 +183,8 @@ mod test_from_raw {
             delay: 0,
             delay_on_para: (0, 0),
             id_bridge: false,
+            after_terminal: false,
+            retry: None,
         };
         let raw = RawRelation {
             from_meta: "B:from:1".to_string(),
@@ -172,6 +198,47 @@ mod test_from_raw {
         assert_eq!(rtn.is_ok(), true);
     }
 
+    #[test]
+    fn session_needs_state_target() {
+        let session = |to_meta: &str, append_para: Vec<u8>| {
+            let mut settings = RelationSettings::default();
+            settings.executor = Some(Executor {
+                protocol: Protocol::BuiltIn,
+                url: "time_range".to_string(),
+                settings: r#"{"unit":"m","session_gap":30}"#.to_string(),
+            });
+            settings.target.append_para = append_para;
+            let raw = RawRelation {
+                from_meta: "B:from:1".to_string(),
+                to_meta: to_meta.to_string(),
+                settings: serde_json::to_string(&settings).unwrap(),
+                flag: 1,
+            };
+            Runtime::new().unwrap().block_on(Relation::from_raw(raw, &MetaCacheStateMock {}, &MetaMock {}))
+        };
+        assert!(session("B:to:1", vec![0]).is_err());
+        assert!(session("B:state:1", vec![]).is_err());
+        assert!(session("B:state:1", vec![0]).is_ok());
+        // resolved once and carried by the mission
+        let rtn = session("B:state:1", vec![0]).unwrap();
+        assert!(rtn.session);
+        assert!(Mission::from(rtn).session);
+    }
+
+    #[derive(Copy, Clone)]
+    struct MetaCacheStateMock;
+
+    #[async_trait]
+    impl MetaCache for MetaCacheStateMock {
+        async fn get<M>(&self, m: &str, _getter: &M) -> Result<Meta> where M: MetaDao {
+            let mut rtn = Meta::from_string(m)?;
+            if m.eq("B:state:1") {
+                let _ = rtn.set_setting(r#"{"is_state":true}"#);
+            }
+            Ok(rtn)
+        }
+    }
+
     #[derive(Copy, Clone)]
     struct MetaCacheMasterMock;
 
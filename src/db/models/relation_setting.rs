@@ -1,4 +1,4 @@
-use crate::common::{Executor, is_default};
+use crate::common::{Executor, is_default, RetryPolicy};
 use crate::db::FlowSelector;
 use crate::db::relation_target::RelationTarget;
 
@@ -40,6 +40,15 @@ pub struct RelationSettings {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     pub id_bridge: bool,
+    /// fire this relation even if the upstream instance is in a terminal state.
+    /// Without it the relation is not fired for the version which enters the terminal state either.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub after_terminal: bool,
+    /// how to retry the convert task when it failed, the `retry` of the target meta will be used if absent
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
 }
 
 #[cfg(test)]
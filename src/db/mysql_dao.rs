@@ -1,5 +1,3 @@
-use std::env;
-
 use mysql_async::{Conn, Params, Pool, Row};
 use mysql_async::error::{DriverError, Error};
 use mysql_async::prelude::*;
@@ -9,7 +7,7 @@ pub use meta_dao::*;
 pub use relation_dao::*;
 pub use task_dao::*;
 
-use crate::common::{NatureError, Result};
+use crate::common::{CONFIG, NatureError, Result};
 
 pub mod task_check;
 
@@ -57,7 +55,16 @@ impl MySql {
     }
 
 
-    async fn get_conn() -> Result<Conn> {
+    /// check whether the database is reachable
+    pub async fn ping() -> Result<()> {
+        let conn = MySql::get_conn().await?;
+        match conn.ping().await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MysqlError(e).into())
+        }
+    }
+
+    pub async fn get_conn() -> Result<Conn> {
         match POOL.get_conn().await {
             Ok(conn) => Ok(conn),
             Err(e) => Err(MysqlError(e).into())
@@ -66,9 +73,7 @@ impl MySql {
 }
 
 fn get_conn() -> Pool {
-    let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
-    Pool::new(database_url)
+    Pool::new(CONFIG.db.database_url.clone())
 }
 
 
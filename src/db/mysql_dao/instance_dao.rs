@@ -54,7 +54,7 @@ impl InstanceDaoImpl {
         }
     }
 
-    async fn get_last_state(f_para: &KeyCondition) -> Result<Option<Instance>> {
+    pub async fn get_last_state(f_para: &KeyCondition) -> Result<Option<Instance>> {
         let sql = r"SELECT meta, ins_id, para, content, context, states, state_version, create_time, sys_context, from_key
             FROM instances
             where meta = :meta and ins_id = :ins_id and para = :para
@@ -74,6 +74,51 @@ impl InstanceDaoImpl {
         }
     }
 
+    /// the last state of the latest session, the para of the session is `<key>/<start>`
+    pub async fn get_last_session(f_para: &KeyCondition) -> Result<Option<Instance>> {
+        let sql = r"SELECT meta, ins_id, para, content, context, states, state_version, create_time, sys_context, from_key
+            FROM instances
+            where meta = :meta and ins_id = :ins_id and para like :para
+            order by create_time desc, state_version desc
+            limit 1";
+        let key = f_para.para.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let p = params! {
+            "meta" => f_para.meta.to_string(),
+            "ins_id" => f_para.id,
+            "para" => format!("{}{}%", key, *SEPARATOR_INS_PARA),
+        };
+        let rtn = MySql::fetch(sql, p, RawInstance::from).await?;
+        match rtn.len() {
+            1 => Ok(Some(rtn[0].to()?)),
+            0 => Ok(None),
+            _ => Err(NatureError::LogicalError("should not return more than one rows".to_string()))
+        }
+    }
+
+    /// the versions of a state-instance after `f_para.state_version`, ordered by `state_version`,
+    /// at most `QUERY_SIZE_LIMIT` ones. Use the last `state_version` returned to get the next page.
+    pub async fn get_state_history(f_para: &KeyCondition) -> Result<Vec<StateChange>> {
+        // the version `state_version` itself is loaded to get the changes of the next one
+        let sql = r"SELECT meta, ins_id, para, content, context, states, state_version, create_time, sys_context, from_key
+            FROM instances
+            where meta = :meta and ins_id = :ins_id and para = :para and state_version >= :state_version
+            order by state_version asc
+            limit :limit";
+        let p = params! {
+            "meta" => f_para.meta.to_string(),
+            "ins_id" => f_para.id,
+            "para" => f_para.para.to_string(),
+            "state_version" => f_para.state_version,
+            "limit" => *QUERY_SIZE_LIMIT + 1,
+        };
+        let result = MySql::fetch(sql, p, RawInstance::from).await?;
+        let mut list: Vec<Instance> = vec![];
+        for one in result {
+            list.push(one.to()?)
+        }
+        Ok(StateChange::page(&list, f_para.state_version, *QUERY_SIZE_LIMIT as usize))
+    }
+
     pub async fn get_by_id(f_para: KeyCondition) -> Result<Option<Instance>> {
         let sql = r"SELECT meta, ins_id, para, content, context, states, state_version, create_time, sys_context, from_key
             FROM instances
@@ -107,7 +152,24 @@ impl InstanceDaoImpl {
         Ok(rtn)
     }
 
-    /// get downstream instance through upstream instance
+    /// query instances of a meta page by page, ordered by `create_time`
+    pub async fn get_by_meta(q: &QueryByMeta) -> Result<InstancePage> {
+        let (sql, p) = build_by_meta(q)?;
+        let result = MySql::fetch(sql, p, RawInstance::from).await?;
+        let mut list: Vec<Instance> = vec![];
+        for one in result {
+            list.push(one.to()?)
+        }
+        let next = if list.len() as i32 == page_limit(q.limit) {
+            list.last().map(QueryCursor::from)
+        } else {
+            None
+        };
+        Ok(InstancePage { list, next })
+    }
+
+    /// get downstream instance through upstream instance,
+    /// `LogicalError` will be returned if the downstream instance is in a terminal state.
     pub async fn get_last_target(from: &Instance, mission: &mut Mission) -> Result<Option<Instance>> {
         // init for MetaType::loop --------------------
         if mission.to.get_meta_type() == MetaType::Loop
@@ -147,7 +209,15 @@ impl InstanceDaoImpl {
         let meta = mission.to.meta_string();
         debug!("get last state for meta {}", &meta);
         let qc = KeyCondition::new(u64::from_str(&id)?, &meta, &para_id, 0);
-        Self::get_last_state(&qc).await
+        let last = if mission.session {
+            Self::get_last_session(&qc).await?
+        } else {
+            Self::get_last_state(&qc).await?
+        };
+        if let Some(ins) = &last {
+            mission.to.check_terminal(&ins.states)?;
+        }
+        Ok(last)
     }
 }
 
@@ -221,6 +291,87 @@ impl KeyRange for InstanceDaoImpl {
     }
 }
 
+fn page_limit(limit: i32) -> i32 {
+    if limit < 1 {
+        1
+    } else if limit < *QUERY_SIZE_LIMIT {
+        limit
+    } else { *QUERY_SIZE_LIMIT }
+}
+
+/// generate sql and parameters for `QueryByMeta`
+fn build_by_meta(q: &QueryByMeta) -> Result<(String, Vec<(String, Value)>)> {
+    if q.meta.is_empty() {
+        return Err(NatureError::VerifyError("meta can not be empty!".to_string()));
+    }
+    let mut list: Vec<String> = vec![];
+    let mut p: Vec<(String, Value)> = params! {
+        "meta" => q.meta.to_string(),
+        "limit" => page_limit(q.limit),
+    };
+    let mut add = |sql: &str, name: &str, value: Value| {
+        list.push(sql.to_string());
+        p.push((name.to_string(), value));
+    };
+    if let Some(id) = q.id {
+        add(" and ins_id = :ins_id", "ins_id", id.into());
+    }
+    if let Some(para) = &q.para_like {
+        add(" and para like :para_like", "para_like", para.to_string().into());
+    }
+    if let Some(t) = q.create_time_gt {
+        add(" and create_time > :time_gt", "time_gt", Local.timestamp_millis(t).naive_local().into());
+    }
+    if let Some(t) = q.create_time_ge {
+        add(" and create_time >= :time_ge", "time_ge", Local.timestamp_millis(t).naive_local().into());
+    }
+    if let Some(t) = q.create_time_lt {
+        add(" and create_time < :time_lt", "time_lt", Local.timestamp_millis(t).naive_local().into());
+    }
+    if let Some(v) = q.state_version_ge {
+        add(" and state_version >= :ver_ge", "ver_ge", v.into());
+    }
+    if let Some(v) = q.state_version_le {
+        add(" and state_version <= :ver_le", "ver_le", v.into());
+    }
+    for (i, s) in q.states_include.iter().enumerate() {
+        let name = format!("state_in{}", i);
+        let sql = format!(" and JSON_CONTAINS(IFNULL(states, '[]'), :{})", name);
+        add(&sql, &name, serde_json::to_string(s)?.into());
+    }
+    for (i, s) in q.states_exclude.iter().enumerate() {
+        let name = format!("state_ex{}", i);
+        let sql = format!(" and NOT JSON_CONTAINS(IFNULL(states, '[]'), :{})", name);
+        add(&sql, &name, serde_json::to_string(s)?.into());
+    }
+    for (i, (k, v)) in q.context.iter().enumerate() {
+        if k.is_empty() || k.contains('"') || k.contains('\\') {
+            let msg = format!("illegal context key for query: [{}]", k);
+            return Err(NatureError::VerifyError(msg));
+        }
+        let path = format!("ctx_k{}", i);
+        let value = format!("ctx_v{}", i);
+        let sql = format!(" and JSON_UNQUOTE(JSON_EXTRACT(context, :{})) = :{}", path, value);
+        list.push(sql);
+        p.push((path, format!("$.\"{}\"", k).into()));
+        p.push((value, v.to_string().into()));
+    }
+    let (sign, order) = if q.create_time_desc { ("<", "desc") } else { (">", "asc") };
+    if let Some(c) = &q.cursor {
+        list.push(format!(" and (create_time, ins_id, para, state_version) {} (:c_time, :c_id, :c_para, :c_ver)", sign));
+        p.push(("c_time".to_string(), Local.timestamp_millis(c.create_time).naive_local().into()));
+        p.push(("c_id".to_string(), c.id.into()));
+        p.push(("c_para".to_string(), c.para.to_string().into()));
+        p.push(("c_ver".to_string(), c.state_version.into()));
+    }
+    let sql = format!("SELECT meta, ins_id, para, content, context, states, state_version, create_time, sys_context, from_key
+            FROM instances
+            where meta = :meta{}
+            order by create_time {}, ins_id {}, para {}, state_version {}
+            limit :limit", list.join(""), order, order, order, order);
+    Ok((sql, p))
+}
+
 fn key_to_part(key: &str) -> Vec<String> {
     if key.is_empty() {
         return vec![];
@@ -471,4 +622,62 @@ mod build_for_part_test {
         assert_eq!(" and para > 'a'", list[2]);
         assert_eq!(" and para < 'b'", list[3]);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod build_by_meta_test {
+    use super::*;
+
+    #[test]
+    fn meta_can_not_be_empty() {
+        let q = QueryByMeta::new("");
+        assert!(build_by_meta(&q).is_err());
+    }
+
+    #[test]
+    fn only_meta() {
+        let q = QueryByMeta::new("B:sale/order:1");
+        let (sql, p) = build_by_meta(&q).unwrap();
+        assert!(sql.contains("where meta = :meta\n"));
+        assert!(sql.contains("order by create_time asc, ins_id asc, para asc, state_version asc"));
+        assert_eq!(2, p.len());
+    }
+
+    #[test]
+    fn desc_with_cursor() {
+        let mut q = QueryByMeta::new("B:sale/order:1");
+        q.create_time_desc = true;
+        q.cursor = Some(QueryCursor::default());
+        let (sql, p) = build_by_meta(&q).unwrap();
+        assert!(sql.contains(" and (create_time, ins_id, para, state_version) < (:c_time, :c_id, :c_para, :c_ver)"));
+        assert!(sql.contains("order by create_time desc, ins_id desc, para desc, state_version desc"));
+        assert_eq!(6, p.len());
+    }
+
+    #[test]
+    fn states_and_context() {
+        let mut q = QueryByMeta::new("B:sale/order:1");
+        q.states_include = vec!["paid".to_string()];
+        q.states_exclude = vec!["cancelled".to_string()];
+        q.context.insert("shop".to_string(), "a".to_string());
+        let (sql, p) = build_by_meta(&q).unwrap();
+        assert!(sql.contains(" and JSON_CONTAINS(IFNULL(states, '[]'), :state_in0)"));
+        assert!(sql.contains(" and NOT JSON_CONTAINS(IFNULL(states, '[]'), :state_ex0)"));
+        assert!(sql.contains(" and JSON_UNQUOTE(JSON_EXTRACT(context, :ctx_k0)) = :ctx_v0"));
+        assert_eq!(p[2], ("state_in0".to_string(), Value::from("\"paid\"")));
+        assert_eq!(p[4], ("ctx_k0".to_string(), Value::from("$.\"shop\"")));
+    }
+
+    #[test]
+    fn illegal_context_key() {
+        let mut q = QueryByMeta::new("B:sale/order:1");
+        q.context.insert("a\"b".to_string(), "a".to_string());
+        assert!(build_by_meta(&q).is_err());
+    }
+
+    #[test]
+    fn limit_test() {
+        assert_eq!(1, page_limit(0));
+        assert_eq!(10, page_limit(10));
+        assert_eq!(*QUERY_SIZE_LIMIT, page_limit(i32::MAX));
+    }
+}
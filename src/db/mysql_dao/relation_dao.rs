@@ -116,6 +116,8 @@ impl RelationDao for RelationDaoImpl {
                 delay: 0,
                 delay_on_para: (0, 0),
                 id_bridge: false,
+                after_terminal: false,
+                retry: None,
             },
         )?;
         let _ = D_R.insert(one.clone()).await;
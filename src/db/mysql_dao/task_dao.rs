@@ -1,8 +1,9 @@
-use chrono::{Duration, Local};
+use chrono::{Duration, Local, Timelike};
 use mysql_async::{params, Value};
 
-use crate::common::{NatureError, Result};
-use crate::db::MySql;
+use crate::channels::CHANNEL_DEAD_LETTER;
+use crate::common::{CONFIG, DeadLetter, MetaType, NatureError, Result, SEPARATOR_META};
+use crate::db::{MySql, TaskType};
 use crate::db::raw_models::{RawTask, RawTaskError};
 
 lazy_static! {
@@ -15,15 +16,24 @@ pub trait TaskDao {
     async fn delete(&self, _record_id: &u64) -> Result<u64>;
     async fn delete_finished(&self, _delay: i64) -> Result<u64>;
     async fn raw_to_error(&self, err: &NatureError, raw: &RawTask) -> Result<u64>;
-    async fn get_overdue(&self, delay: i64, _limit: i64) -> Result<Vec<RawTask>>;
+    async fn get_overdue(&self, lease: &Lease, delay: i64, _limit: i64) -> Result<Vec<RawTask>>;
     async fn update_execute_time(&self, _record_id: &u64, delay: i64) -> Result<u64>;
     async fn finish_task(&self, _record_id: &u64) -> Result<u64>;
     async fn increase_times_and_delay(&self, _record_id: &u64, delay: i32) -> Result<u64>;
     async fn get(&self, _record_id: &u64) -> Result<Option<RawTask>>;
+    async fn get_loops(&self, limit: i64) -> Result<Vec<RawTask>>;
+    async fn redo_with_data(&self, raw: &RawTask) -> Result<u64>;
 }
 
 pub struct TaskDaoImpl;
 
+/// a retry worker holds the tasks it loaded for `seconds`, other workers can't load them until the lease expired.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lease {
+    pub owner: String,
+    pub seconds: i64,
+}
+
 #[async_trait]
 impl TaskDao for TaskDaoImpl {
     async fn insert(&self, raw: &RawTask) -> Result<u64> {
@@ -77,16 +87,19 @@ impl TaskDao for TaskDaoImpl {
         Ok(rtn)
     }
 
+    /// the dead letter will be sent if configured
     async fn raw_to_error(&self, err: &NatureError, raw: &RawTask) -> Result<u64> {
         let sql = r"INSERT INTO task_error
             (task_id, task_key, task_type, task_for, `data`, create_time, msg)
             VALUES(:task_id, :task_key, :task_type, :task_for, :data, :create_time, :msg)";
 
         let rd = RawTaskError::from_raw(err, raw);
+        let msg = rd.msg.clone();
         let p: Vec<(String, Value)> = rd.into();
         let num = match MySql::idu(sql, p).await {
             Ok(num) => {
                 self.delete(&raw.task_id).await?;
+                send_dead_letter(msg, raw);
                 num
             }
             Err(NatureError::DaoDuplicated(_)) => {
@@ -98,24 +111,43 @@ impl TaskDao for TaskDaoImpl {
         Ok(num)
     }
 
-    async fn get_overdue(&self, delay: i64, _limit: i64) -> Result<Vec<RawTask>> {
-        let sql = r"SELECT task_id, task_key, task_type, task_for, task_state, `data`, create_time, execute_time, retried_times
-            FROM task
-            WHERE execute_time < :execute_time and task_state = 0
+    /// lease the overdue tasks to `lease.owner` first, so that the other retry workers won't load them again.
+    /// the tasks whose lease expired can be leased by anyone.
+    async fn get_overdue(&self, lease: &Lease, delay: i64, _limit: i64) -> Result<Vec<RawTask>> {
+        let sql = r"UPDATE task
+            SET lease_owner = :lease_owner, lease_expire = :lease_expire
+            WHERE execute_time < :execute_time and task_state = 0 and (lease_expire IS NULL or lease_expire < :now)
+            ORDER BY execute_time
             LIMIT :limit";
 
-        let _execute_time = Local::now().checked_add_signed(Duration::seconds(delay)).unwrap().naive_local();
+        let now = Local::now();
+        let _execute_time = now.checked_add_signed(Duration::seconds(delay)).unwrap().naive_local();
+        // DATETIME has no fraction, keep the same value to find the leased tasks
+        let _lease_expire = now.checked_add_signed(Duration::seconds(lease.seconds)).unwrap().naive_local().with_nanosecond(0).unwrap();
         let p = params! {
+            "lease_owner" => lease.owner.as_str(),
+            "lease_expire" => _lease_expire,
             "execute_time" => _execute_time,
+            "now" => now.naive_local(),
             "limit" => _limit,
         };
+        if MySql::idu(sql, p).await? == 0 {
+            return Ok(vec![]);
+        }
 
+        let sql = r"SELECT task_id, task_key, task_type, task_for, task_state, `data`, create_time, execute_time, retried_times
+            FROM task
+            WHERE lease_owner = :lease_owner and lease_expire = :lease_expire and task_state = 0";
+        let p = params! {
+            "lease_owner" => lease.owner.as_str(),
+            "lease_expire" => _lease_expire,
+        };
         MySql::fetch(sql, p, RawTask::from).await
     }
 
     async fn update_execute_time(&self, _record_id: &u64, delay: i64) -> Result<u64> {
         let sql = r"UPDATE nature.task
-            SET execute_time=:execute_time
+            SET execute_time=:execute_time, lease_owner = '', lease_expire = NULL
             WHERE task_id=:task_id";
 
         let _time = Local::now().checked_add_signed(Duration::seconds(delay)).unwrap().naive_local();
@@ -145,10 +177,10 @@ impl TaskDao for TaskDaoImpl {
         Ok(rtn)
     }
 
-    /// increase one times and delay `delay` seconds
+    /// increase one times and delay `delay` seconds, the lease is released too.
     async fn increase_times_and_delay(&self, _record_id: &u64, delay: i32) -> Result<u64> {
         let sql = r"UPDATE nature.task
-            SET execute_time=:execute_time, retried_times = retried_times+1
+            SET execute_time=:execute_time, retried_times = retried_times+1, lease_owner = '', lease_expire = NULL
             WHERE task_id=:task_id";
 
         let _time = Local::now().checked_add_signed(Duration::seconds(delay as i64)).unwrap().naive_local();
@@ -176,6 +208,55 @@ impl TaskDao for TaskDaoImpl {
             _ => Err(NatureError::SystemError("should less than 2 record return".to_string())),
         }
     }
+
+    /// unfinished convert tasks which target to `MetaType::Loop`
+    async fn get_loops(&self, limit: i64) -> Result<Vec<RawTask>> {
+        let sql = r"SELECT task_id, task_key, task_type, task_for, task_state, `data`, create_time, execute_time, retried_times
+            FROM task
+            WHERE task_type = :task_type and task_state = 0 and task_for like :task_for
+            ORDER BY task_id
+            LIMIT :limit";
+
+        let p = params! {
+            "task_type" => TaskType::Convert as i8,
+            "task_for" => format!("{}{}%", MetaType::Loop.get_prefix(), *SEPARATOR_META),
+            "limit" => limit,
+        };
+
+        MySql::fetch(sql, p, RawTask::from).await
+    }
+
+    /// replace the `data` and let the retry program redo it now, 0 will be returned if it is leased or finished.
+    async fn redo_with_data(&self, raw: &RawTask) -> Result<u64> {
+        let sql = r"UPDATE nature.task
+            SET `data` = :data, execute_time = :now, lease_owner = '', lease_expire = NULL
+            WHERE task_id = :task_id and task_state = 0 and (lease_expire IS NULL or lease_expire < :now)";
+
+        let p = params! {
+            "data" => raw.data.as_str(),
+            "now" => Local::now().naive_local(),
+            "task_id" => raw.task_id,
+        };
+        MySql::idu(sql, p).await
+    }
+}
+
+fn send_dead_letter(msg: String, raw: &RawTask) {
+    if !CONFIG.dead_letter.is_enabled() {
+        return;
+    }
+    let letter = DeadLetter {
+        task_id: raw.task_id,
+        task_key: raw.task_key.clone(),
+        task_type: raw.task_type,
+        task_for: raw.task_for.clone(),
+        msg,
+        data: DeadLetter::decode(&raw.data),
+        create_time: raw.create_time,
+    };
+    if let Err(e) = CHANNEL_DEAD_LETTER.send(letter) {
+        warn!("send dead letter failed: {}", e);
+    }
 }
 
 #[cfg(test)]
@@ -205,4 +286,27 @@ mod test {
         let get_task = D_T.get(&1).await.unwrap();
         assert!(get_task.is_none());
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn lease_test() {
+        env::set_var("DATABASE_URL", CONN_STR);
+        let mut task = RawTask {
+            task_key: "lease_test".to_string(),
+            ..Default::default()
+        };
+        task.execute_time -= Duration::seconds(10);
+        task.task_id = D_T.insert(&task).await.unwrap();
+        let a = Lease { owner: "a".to_string(), seconds: 2 };
+        let b = Lease { owner: "b".to_string(), seconds: 2 };
+        let leased = D_T.get_overdue(&a, 0, 100).await.unwrap();
+        assert!(leased.iter().any(|t| t.task_id == task.task_id));
+        let leased = D_T.get_overdue(&b, 0, 100).await.unwrap();
+        assert!(!leased.iter().any(|t| t.task_id == task.task_id));
+        // expired lease can be reclaimed
+        tokio::time::delay_for(std::time::Duration::from_secs(3)).await;
+        let leased = D_T.get_overdue(&b, 0, 100).await.unwrap();
+        assert!(leased.iter().any(|t| t.task_id == task.task_id));
+        D_T.delete(&task.task_id).await.unwrap();
+    }
 }
\ No newline at end of file
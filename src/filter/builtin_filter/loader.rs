@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use crate::common::{CONTEXT_LOOP_FINISHED, CONTEXT_LOOP_ID, CONTEXT_LOOP_NEXT, Executor, get_para_part, Instance, is_default, KeyCondition, NatureError, Result};
+use crate::common::{CONTEXT_LOOP_FINISHED, CONTEXT_LOOP_ID, CONTEXT_LOOP_NEXT, Executor, get_para_part, Instance, is_default, KeyCondition, NatureError, Result, Zone};
 use crate::db::KeyRange;
 use crate::filter::builtin_filter::FilterBefore;
 use crate::filter::convert_before;
@@ -10,7 +10,7 @@ pub struct Loader {
     pub dao: Arc<dyn KeyRange>
 }
 
-/// **notice** can only load one page data! more page consider use with `MetaType::Loop`
+/// **notice** can only load one page data unless `max_pages` is set! more page consider use with `MetaType::Loop`
 #[async_trait]
 impl FilterBefore for Loader {
     async fn filter(&self, ins: &mut Instance, cfg: &str) -> Result<()> {
@@ -24,8 +24,8 @@ impl FilterBefore for Loader {
         let time_range = match &setting.time_part {
             Some(part) => match get_para_part(&ins.para, part) {
                 Ok(pair) => (
-                    Some(i64::from_str(&pair[0])? * 1000),
-                    Some(i64::from_str(&pair[1])? * 1000)
+                    Some(setting.timezone.parse_time(&pair[0])?),
+                    Some(setting.timezone.parse_time(&pair[1])?)
                 ),
                 Err(e) => {
                     let msg = format!("built-in::Loader instance's para has no time info: {}", e.to_string());
@@ -34,9 +34,12 @@ impl FilterBefore for Loader {
             },
             None => (None, None)
         };
+        if setting.max_pages > 0 {
+            return self.load_all(ins, &setting, time_range).await;
+        }
         let first = match ins.sys_context.get(CONTEXT_LOOP_NEXT) {
             Some(first) => first.to_string(),
-            None => setting.key_gt,
+            None => setting.key_gt.to_string(),
         };
         debug!("loader for: {}, condition first: {}", ins.meta, first);
 
@@ -48,21 +51,8 @@ impl FilterBefore for Loader {
         ins.sys_context.insert(CONTEXT_LOOP_ID.to_string(), loop_id.to_string());
 
         // load
-        let condition = KeyCondition {
-            id: 0,
-            meta: "".to_string(),
-            key_gt: first,
-            key_ge: "".to_string(),
-            key_lt: setting.key_lt,
-            key_le: "".to_string(),
-            para: "".to_string(),
-            state_version: 0,
-            time_ge: time_range.0,
-            time_lt: time_range.1,
-            limit: setting.page_size as i32,
-        };
         let mut content: Vec<String> = vec![];
-        let rtn: Vec<Instance> = self.dao.get_by_key_range(&condition).await?;
+        let rtn = self.load(&setting, first, time_range, setting.page_size).await?;
         let len = rtn.len();
         debug!("loaded records: {} for: {} ", len, ins.meta);
 
@@ -86,6 +76,59 @@ impl FilterBefore for Loader {
     }
 }
 
+impl Loader {
+    async fn load(&self, setting: &Setting, first: String, time_range: (Option<i64>, Option<i64>), limit: u16) -> Result<Vec<Instance>> {
+        let condition = KeyCondition {
+            id: 0,
+            meta: "".to_string(),
+            key_gt: first,
+            key_ge: "".to_string(),
+            key_lt: setting.key_lt.to_string(),
+            key_le: "".to_string(),
+            para: "".to_string(),
+            state_version: 0,
+            time_ge: time_range.0,
+            time_lt: time_range.1,
+            limit: limit as i32,
+        };
+        self.dao.get_by_key_range(&condition).await
+    }
+
+    /// load all the pages in one invocation, so that `MetaType::Loop` is not needed.
+    async fn load_all(&self, ins: &mut Instance, setting: &Setting, time_range: (Option<i64>, Option<i64>)) -> Result<()> {
+        let mut content: Vec<String> = vec![];
+        let mut first = setting.key_gt.to_string();
+        let mut pages = 0;
+        loop {
+            let rtn = self.load(setting, first.clone(), time_range, setting.page_size).await?;
+            let len = rtn.len();
+            pages += 1;
+            debug!("loaded page: {}, records: {} for: {} ", pages, len, ins.meta);
+            if let Some(last) = rtn.last() {
+                first = last.get_key();
+            }
+            for mut one in rtn {
+                convert_before(&mut one, setting.filters.clone()).await?;
+                content.push(one.content.to_string());
+            }
+            if len < setting.page_size as usize {
+                break;
+            }
+            if pages == setting.max_pages {
+                // make sure there is no more data
+                if !self.load(setting, first, time_range, 1).await?.is_empty() {
+                    let msg = format!("built-in::Loader more than {} pages for: {}, please increase `max_pages` or use `MetaType::Loop`", pages, ins.meta);
+                    return Err(NatureError::VerifyError(msg));
+                }
+                break;
+            }
+        }
+        ins.content = serde_json::to_string(&content)?;
+        debug!("loaded content for: {} is: {}", ins.meta, ins.content);
+        Ok(())
+    }
+}
+
 
 /// when used this mode the target `MetaType` must be `Multi`
 #[derive(Serialize, Deserialize, Debug)]
@@ -97,9 +140,19 @@ struct Setting {
     #[serde(skip_serializing_if = "is_100")]
     #[serde(default = "default_100")]
     page_size: u16,
+    /// load at most `max_pages` pages in one invocation, and the `Instance` need not to be `MetaType::Loop`.
+    /// error will be returned if there are more data. 0 means only one page will be loaded each time.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    max_pages: u16,
     /// where to get the time range from the `Instance.para` which used to load data from Instance table
     /// it only accept two element, one for Begin Time and the other for End Time
     time_part: Option<Vec<u8>>,
+    /// used to parse the time which is not seconds from the epoch, for example "2020-08-01",
+    /// IANA name or fixed offset, empty means the time zone of the server.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    timezone: Zone,
     /// correct the format of the data loaded.
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
@@ -112,6 +165,9 @@ impl Setting {
             return Err(NatureError::VerifyError("builtin-filter loader `settings` can't be empty".to_string()));
         };
         let result: Setting = serde_json::from_str(cfg)?;
+        if result.page_size == 0 {
+            return Err(NatureError::VerifyError("builtin-filter loader `settings.page_size` can't be 0".to_string()));
+        }
         if let Some(part) = &result.time_part {
             if part.len() != 2 {
                 return Err(NatureError::VerifyError("builtin-filter loader `settings.time_part` need exactly 2 elements".to_string()));
@@ -161,6 +217,19 @@ mod loader_test {
         assert_eq!("[\"one\",\"two\"]", instance.content);
     }
 
+    #[tokio::test]
+    async fn time_with_zone() {
+        let loader = Loader { dao: Arc::new(Mocker {}) };
+        let mut instance = Instance::default();
+        instance.para = "2020-08-01/2020-08-02".to_string();
+        let setting = r#"{"key_gt":"abc","key_lt":"def","time_part":[0,1],"timezone":"+08:00"}"#;
+        let rtn = loader.filter(&mut instance, setting).await;
+        assert!(rtn.is_ok());
+        let setting = r#"{"key_gt":"abc","key_lt":"def","time_part":[0,1],"timezone":"Mars/Base"}"#;
+        let rtn = loader.filter(&mut instance, setting).await;
+        assert!(rtn.is_err());
+    }
+
     #[tokio::test]
     async fn instance_para_not_set() {
         let loader = Loader { dao: Arc::new(Mocker {}) };
@@ -171,6 +240,42 @@ mod loader_test {
         assert_eq!(true, err.to_string().contains("built-in"));
     }
 
+    #[tokio::test]
+    async fn multi_pages() {
+        let loader = Loader { dao: Arc::new(PageMocker {}) };
+        let mut instance = Instance::default();
+        let setting = r#"{"key_gt":"","key_lt":"z","page_size":2,"max_pages":3}"#;
+        loader.filter(&mut instance, setting).await.unwrap();
+        assert_eq!("[\"1\",\"2\",\"3\",\"4\",\"5\"]", instance.content);
+        assert!(instance.sys_context.is_empty());
+
+        // just fill the pages
+        let setting = r#"{"key_gt":"","key_lt":"z","page_size":5,"max_pages":1}"#;
+        loader.filter(&mut instance, setting).await.unwrap();
+        assert_eq!("[\"1\",\"2\",\"3\",\"4\",\"5\"]", instance.content);
+
+        // more than max pages
+        let setting = r#"{"key_gt":"","key_lt":"z","page_size":2,"max_pages":2}"#;
+        let err = loader.filter(&mut instance, setting).await.err().unwrap();
+        assert!(err.to_string().contains("max_pages"));
+    }
+
+    /// 5 instances with para "1" to "5"
+    struct PageMocker;
+
+    #[async_trait]
+    impl KeyRange for PageMocker {
+        async fn get_by_key_range(&self, para: &KeyCondition) -> Result<Vec<Instance>> {
+            let rtn = (1..=5).map(|i| {
+                let mut one = Instance::default();
+                one.para = i.to_string();
+                one.content = i.to_string();
+                one
+            }).filter(|one| one.get_key() > para.key_gt).take(para.limit as usize).collect();
+            Ok(rtn)
+        }
+    }
+
     struct Mocker;
 
     #[async_trait]
@@ -210,6 +315,13 @@ mod setting_test {
         assert_eq!(NatureError::VerifyError("builtin-filter loader `settings.time_part` need exactly 2 elements".to_string()), err);
     }
 
+    #[test]
+    fn page_size_is_zero() {
+        let s = r#"{"key_gt":"abc","key_lt":"def","page_size":0,"max_pages":3}"#;
+        let err = Setting::get(s).err().unwrap();
+        assert_eq!(NatureError::VerifyError("builtin-filter loader `settings.page_size` can't be 0".to_string()), err);
+    }
+
     #[test]
     fn time_part_not_set() {
         let s = r#"{"key_gt":"abc","key_lt":"def"}"#;
@@ -266,7 +378,9 @@ mod test_ignore {
             key_gt: "".to_string(),
             key_lt: "".to_string(),
             page_size: 0,
+            max_pages: 0,
             time_part: None,
+            timezone: Default::default(),
             filters: vec![para_as_key],
         };
         loader.settings = serde_json::to_string(&loader_setting).unwrap();
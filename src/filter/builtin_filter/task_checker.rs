@@ -1,8 +1,6 @@
-use std::str::FromStr;
-
 use chrono::{Local, TimeZone};
 
-use crate::common::{get_para_part, Instance, is_default, NatureError, Result};
+use crate::common::{get_para_part, Instance, is_default, NatureError, Result, Zone};
 use crate::db::task_check::{Condition, TaskChecker};
 use crate::filter::builtin_filter::FilterBefore;
 
@@ -45,6 +43,11 @@ struct Setting {
     #[serde(skip_serializing_if = "is_default")]
     #[serde(default)]
     time_part: Option<Vec<u8>>,
+    /// used to parse the time which is not seconds from the epoch, for example "2020-08-01",
+    /// IANA name or fixed offset, empty means the time zone of the server.
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    timezone: Zone,
 }
 
 impl Setting {
@@ -53,8 +56,8 @@ impl Setting {
         let part = match &self.time_part {
             Some(part) => match get_para_part(&ins.para, part) {
                 Ok(rtn) => {
-                    let t_ge = i64::from_str(&rtn[0])? * 1000;
-                    let t_lt = i64::from_str(&rtn[1])? * 1000;
+                    let t_ge = self.timezone.parse_time(&rtn[0])?;
+                    let t_lt = self.timezone.parse_time(&rtn[1])?;
                     let t_ge = Local.timestamp_millis(t_ge).naive_local();
                     let t_lt = Local.timestamp_millis(t_lt).naive_local();
                     (Some(t_ge), Some(t_lt))
@@ -76,4 +79,19 @@ impl Setting {
         };
         Ok(rtn)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn to_condition_test() {
+        let mut ins = Instance::default();
+        ins.para = "2020-08-01/1596297600".to_string();
+        let cfg: Setting = serde_json::from_str(r#"{"key_gt":"a","key_lt":"b","time_part":[0,1],"timezone":"+08:00"}"#).unwrap();
+        let rtn = cfg.to_condition(&ins).unwrap();
+        assert_eq!(rtn.time_ge, Some(Local.timestamp_millis(1596211200000).naive_local()));
+        assert_eq!(rtn.time_lt, Some(Local.timestamp_millis(1596297600000).naive_local()));
+    }
+}
@@ -1,45 +1,86 @@
 use std::env;
 use std::ops::Deref;
+use std::process;
+use std::time::Duration;
 
-use reqwest::Client;
+use chrono::Local;
+use futures::StreamExt;
+use reqwest::{Client, RequestBuilder, Url};
 
 use cfg::*;
 use delay::*;
+use nodes::*;
 use sleep::*;
 
+use crate::channels::start_dead_letter_thread;
 use crate::common::*;
-use crate::db::{D_T, RawTask, TaskDao};
+use crate::controller::handle_dead_letter;
+use crate::db::{D_T, Lease, MissionRaw, RawTask, TaskDao, TaskType};
+use crate::web::{HEADER_API_KEY, HEADER_SIGNATURE, HEADER_TIMESTAMP, sign};
 
 lazy_static! {
-    static ref CLIENT : Client = Client::new();
+    static ref CLIENT : Client = client();
+    static ref NODES : Nodes = Nodes::from_config(&CONFIG.retry);
 }
 
 pub async fn start() {
-    dotenv::dotenv().ok();
     let _ = env_logger::init();
     let mut last_delay: u64 = 0;
-    let base_delay = env::var("BASE_DELAY").unwrap_or_else(|_| "2".to_string()).parse::<i64>().unwrap();
-    let load_size = env::var("LOAD_SIZE").unwrap_or_else(|_| "100".to_string()).parse::<i64>().unwrap();
-    let clean_delay = env::var("CLEAN_DELAY").unwrap_or_else(|_| "2".to_string()).parse::<i64>().unwrap();
+    let base_delay = CONFIG.retry.base_delay;
+    let load_size = CONFIG.retry.load_size;
+    let clean_delay = CONFIG.retry.clean_delay;
+    let lease = lease();
+    if CONFIG.dead_letter.is_enabled() {
+        let _ = start_dead_letter_thread(|letter| handle_dead_letter(letter, input_remote));
+    }
+    info!("----------- {} : {}------------", "lease_owner", lease.owner);
     info!("----------- {} : {}------------", "base_delay", base_delay);
     info!("----------- {} : {}------------", "load_size", load_size);
     info!("----------- {} : {}------------", "clean_delay", clean_delay);
+    info!("----------- {} : {}------------", "concurrency", CONFIG.retry.concurrency);
     loop {
-        last_delay = once(last_delay, base_delay, load_size, clean_delay).await
+        last_delay = once(&lease, last_delay, base_delay, load_size, clean_delay).await
+    }
+}
+
+/// a hung node should fail the request, so that the task can be sent to the other nodes
+fn client() -> Client {
+    let rtn = Client::builder()
+        .connect_timeout(Duration::from_secs(CONFIG.retry.connect_timeout))
+        .timeout(Duration::from_secs(CONFIG.retry.request_timeout))
+        .build();
+    match rtn {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("build http client failed: {}, the default one is used", e);
+            Client::new()
+        }
     }
 }
 
-async fn once(last_delay: u64, base_delay: i64, limit: i64, finish_delay: i64) -> u64 {
+fn lease() -> Lease {
+    let owner = match &CONFIG.retry.worker_id {
+        Some(id) => id.clone(),
+        None => {
+            let host = env::var("HOSTNAME").unwrap_or_else(|_| "retry".to_string());
+            format!("{}-{}-{}", host, process::id(), Local::now().timestamp())
+        }
+    };
+    Lease { owner, seconds: CONFIG.retry.lease }
+}
+
+async fn once(lease: &Lease, last_delay: u64, base_delay: i64, limit: i64, finish_delay: i64) -> u64 {
     debug!("start a new loop");
     let mut len = 0;
-    let rs = D_T.get_overdue(base_delay, limit).await;
+    let rs = D_T.get_overdue(lease, base_delay, limit).await;
     match rs {
         Ok(rs) => {
             len = rs.len();
             debug!("load tasks number: {}", rs.len());
-            for r in rs {
-                let _ = process_delayed(&r).await;
-            }
+            NODES.check_health(&CLIENT).await;
+            futures::stream::iter(rs)
+                .for_each_concurrent(CONFIG.retry.concurrency, |r| async move { process_delayed(&r).await })
+                .await;
         }
         Err(e) => {
             warn!("found error: {}", e)
@@ -55,25 +96,61 @@ async fn once(last_delay: u64, base_delay: i64, limit: i64, finish_delay: i64) -
 
 async fn process_delayed(r: &RawTask) -> () {
     debug!("process task: {:?}", r);
-    let max_times = *MAX_RETRY_TIMES.deref();
+    let policy = retry_policy(r);
+    let max_times = policy.max_attempts(*MAX_RETRY_TIMES.deref());
     if (r.retried_times as usize) < max_times {
-        let req = CLIENT.post(&*NATURE_SERVER_ADDRESS).json(r).send().await;
+        let node = match NODES.pick(&r.task_key) {
+            Some(node) => node,
+            None => {
+                warn!("no healthy node for task: {}", r.task_id);
+                return;
+            }
+        };
+        let req = match redo_request(r, &node.redo) {
+            Ok(req) => req.send().await,
+            Err(e) => {
+                warn!("build request failed: {}", e);
+                return;
+            }
+        };
         match req {
+            Ok(res) if res.status().is_server_error() => {
+                warn!("send task to {} failed: {}", node.redo, res.status());
+                node.set_healthy(false);
+            }
             Ok(_) => {
                 debug!("send task succeed!");
-                let delay = get_delay_by_times(r.retried_times);
+                let delay = get_delay_by_times(&policy, r.retried_times);
                 // 注释掉下一行可用于并发测试
                 if let Err(e) = D_T.increase_times_and_delay(&r.task_id, delay).await {
                     warn!("task update failed: {}", e);
                 }
             }
-            Err(_) => {
-                warn!("send task failed!");
+            Err(e) => {
+                warn!("send task to {} failed: {}", node.redo, e);
+                node.set_healthy(false);
             }
         }
     } else {
         debug!("tried too many times!");
-        let _ = D_T.raw_to_error(&NatureError::EnvironmentError(format!("rtried over max times : {}", max_times)), r).await;
+        let _ = match policy.give_up {
+            GiveUp::Error => D_T.raw_to_error(&NatureError::EnvironmentError(format!("rtried over max times : {}", max_times)), r).await,
+            GiveUp::Drop => D_T.delete(&r.task_id).await,
+        };
+    }
+}
+
+/// only the convert task carries the policy, the others use the default one.
+fn retry_policy(r: &RawTask) -> RetryPolicy {
+    if r.task_type != TaskType::Convert as i8 {
+        return RetryPolicy::default();
+    }
+    match MissionRaw::from_json(&r.data) {
+        Ok(m) => m.retry,
+        Err(e) => {
+            warn!("can't read retry policy from task {}: {}", r.task_id, e);
+            RetryPolicy::default()
+        }
     }
 }
 
@@ -81,4 +158,44 @@ async fn process_delayed(r: &RawTask) -> () {
 pub mod cfg;
 pub mod sleep;
 mod delay;
+mod nodes;
+
+fn redo_request(r: &RawTask, address: &str) -> Result<RequestBuilder> {
+    signed_request(address, serde_json::to_vec(r)?)
+}
+
+/// the retry program has no convert threads, so the dead letter instance is input by a Nature node.
+async fn input_remote(ins: Instance) -> Result<u64> {
+    let node = match NODES.pick(&ins.meta) {
+        Some(node) => node,
+        None => return Err(NatureError::EnvironmentError("no healthy node to input the instance".to_string()))
+    };
+    let req = signed_request(&node.input, serde_json::to_vec(&ins)?)?;
+    let res = match req.send().await {
+        Ok(res) if res.status().is_server_error() => {
+            node.set_healthy(false);
+            return Err(NatureError::EnvironmentError(format!("input to {} failed: {}", node.input, res.status())));
+        }
+        Ok(res) => res,
+        Err(e) => {
+            node.set_healthy(false);
+            return Err(e.into());
+        }
+    };
+    res.json::<Result<u64>>().await?
+}
 
+/// add the authentication headers if configured
+fn signed_request(address: &str, body: Vec<u8>) -> Result<RequestBuilder> {
+    let mut req = CLIENT.post(address).header("Content-Type", "application/json");
+    if let Some(key) = &*NATURE_API_KEY {
+        req = req.header(HEADER_API_KEY, key.as_str());
+    }
+    if let Some(secret) = &*NATURE_API_SECRET {
+        let timestamp = Local::now().timestamp().to_string();
+        let url = Url::parse(address).map_err(|e| NatureError::VerifyError(format!("illegal address {}: {}", address, e)))?;
+        req = req.header(HEADER_SIGNATURE, sign(secret, "POST", url.path(), &timestamp, &body)?)
+            .header(HEADER_TIMESTAMP, timestamp);
+    }
+    Ok(req.body(body))
+}
@@ -1,22 +1,13 @@
-use std::env;
+use crate::common::CONFIG;
 
 lazy_static! {
-    pub static ref MAX_RETRY_TIMES : usize = {
-        env::var("MAX_RETRY_TIMES").unwrap_or_else(|_|"6".to_string()).parse::<usize>().unwrap()
-    };
-    pub static ref FIRST_RETRY_INTERVAL : usize = {
-        env::var("FIRST_RETRY_INTERVAL").unwrap_or_else(|_|"5".to_string()).parse::<usize>().unwrap()
-    };
-    pub static ref MAX_SLEEP : u64 = {
-        env::var("MAX_SLEEP").unwrap_or_else(|_|"60000".to_string()).parse::<u64>().unwrap()
-    };
-    pub static ref MIN_SLEEP : u64 = {
-        env::var("MIN_SLEEP").unwrap_or_else(|_|"1".to_string()).parse::<u64>().unwrap()
-    };
-    pub static ref BUSY_SLEEP : u64 = {
-        env::var("BUSY_SLEEP").unwrap_or_else(|_|"2".to_string()).parse::<u64>().unwrap()
-    };
-    pub static ref NATURE_SERVER_ADDRESS : String = {
-        env::var("NATURE_SERVER_ADDRESS").unwrap_or_else(|_|"http://localhost:8080/redo_task".to_string())
-    };
+    pub static ref MAX_RETRY_TIMES : usize = CONFIG.retry.max_retry_times;
+    pub static ref FIRST_RETRY_INTERVAL : usize = CONFIG.retry.first_retry_interval;
+    pub static ref MAX_SLEEP : u64 = CONFIG.retry.max_sleep;
+    pub static ref MIN_SLEEP : u64 = CONFIG.retry.min_sleep;
+    pub static ref BUSY_SLEEP : u64 = CONFIG.retry.busy_sleep;
+    /// needed when Nature enabled the authentication, the client must have the admin permission
+    pub static ref NATURE_API_KEY : Option<String> = CONFIG.retry.api_key.clone();
+    /// sign the request if it is set
+    pub static ref NATURE_API_SECRET : Option<String> = CONFIG.retry.api_secret.clone();
 }
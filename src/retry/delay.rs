@@ -1,11 +1,13 @@
 use std::ops::Deref;
 
+use chrono::Local;
+
+use crate::common::RetryPolicy;
 use crate::retry::cfg::*;
 
-/// Exponential change based on `FIRST_RETRY_INTERVAL`
-pub fn get_delay_by_times(times: i16) -> i32 {
-    if times == 0 { return *FIRST_RETRY_INTERVAL.deref() as i32; }
-    (1 << times) * 5 as i32
+/// seconds to wait after `times` retries, the interval is `FIRST_RETRY_INTERVAL` unless the policy sets one.
+pub fn get_delay_by_times(policy: &RetryPolicy, times: i16) -> i32 {
+    policy.delay(times, *FIRST_RETRY_INTERVAL.deref() as i32, Local::now().timestamp_subsec_nanos())
 }
 
 #[cfg(test)]
@@ -14,10 +16,11 @@ mod test {
 
     #[test]
     fn test_get_delay_by_times() {
-        assert_eq!(get_delay_by_times(0), 5);
-        assert_eq!(get_delay_by_times(1), 10);
-        assert_eq!(get_delay_by_times(2), 20);
-        assert_eq!(get_delay_by_times(3), 40);
-        assert_eq!(get_delay_by_times(4), 80);
+        let policy = RetryPolicy::default();
+        assert_eq!(get_delay_by_times(&policy, 0), 5);
+        assert_eq!(get_delay_by_times(&policy, 1), 10);
+        assert_eq!(get_delay_by_times(&policy, 2), 20);
+        assert_eq!(get_delay_by_times(&policy, 3), 40);
+        assert_eq!(get_delay_by_times(&policy, 4), 80);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,170 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+
+use crate::common::{Balance, Readiness, RetryConfig};
+
+static REDO_PATH: &str = "/redo_task";
+static READY_PATH: &str = "/ready";
+static INPUT_PATH: &str = "/input";
+
+/// a Nature node which the tasks can be redone on
+#[derive(Debug)]
+pub struct Node {
+    pub redo: String,
+    pub ready: String,
+    pub input: String,
+    healthy: AtomicBool,
+}
+
+impl Node {
+    fn new(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Node {
+            redo: base.to_string() + REDO_PATH,
+            ready: base.to_string() + READY_PATH,
+            input: base.to_string() + INPUT_PATH,
+            healthy: AtomicBool::new(true),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    async fn check_health(&self, client: &Client) {
+        let ready = match client.get(&self.ready).send().await {
+            Ok(res) => match res.json::<Readiness>().await {
+                Ok(r) => r.ready,
+                Err(e) => {
+                    warn!("illegal readiness from {}: {}", self.ready, e);
+                    false
+                }
+            },
+            Err(e) => {
+                warn!("can't reach {}: {}", self.ready, e);
+                false
+            }
+        };
+        self.set_healthy(ready);
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        if self.is_healthy() != healthy {
+            match healthy {
+                true => info!("node recovered: {}", self.redo),
+                false => warn!("node unhealthy: {}", self.redo)
+            }
+        }
+        self.healthy.store(healthy, Ordering::Relaxed);
+    }
+}
+
+/// choose a healthy node for each task
+pub struct Nodes {
+    nodes: Vec<Node>,
+    balance: Balance,
+    next: AtomicUsize,
+    interval: Duration,
+    last_check: Mutex<Option<Instant>>,
+}
+
+impl Nodes {
+    pub fn from_config(cfg: &RetryConfig) -> Self {
+        let nodes = match cfg.nature_servers.is_empty() {
+            true => vec![Node::new(cfg.nature_server_address.trim_end_matches(REDO_PATH))],
+            false => cfg.nature_servers.iter().map(|s| Node::new(s)).collect()
+        };
+        Nodes {
+            nodes,
+            balance: cfg.balance,
+            next: AtomicUsize::new(0),
+            interval: Duration::from_secs(cfg.health_check_interval),
+            last_check: Mutex::new(None),
+        }
+    }
+
+    /// None if all the nodes are unhealthy
+    pub fn pick(&self, task_key: &str) -> Option<&Node> {
+        let healthy: Vec<&Node> = self.nodes.iter().filter(|n| n.is_healthy()).collect();
+        if healthy.is_empty() {
+            return None;
+        }
+        let idx = match self.balance {
+            Balance::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            Balance::Hash => {
+                let mut hasher = DefaultHasher::new();
+                task_key.hash(&mut hasher);
+                hasher.finish() as usize
+            }
+        };
+        Some(healthy[idx % healthy.len()])
+    }
+
+    /// check the `/ready` of all the nodes if `interval` elapsed since last check
+    pub async fn check_health(&self, client: &Client) {
+        {
+            let mut last = self.last_check.lock().unwrap();
+            if let Some(time) = *last {
+                if time.elapsed() < self.interval {
+                    return;
+                }
+            }
+            *last = Some(Instant::now());
+        }
+        // check at the same time, so that a hung node won't delay the others
+        futures::future::join_all(self.nodes.iter().map(|node| node.check_health(client))).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn three_nodes(balance: Balance) -> Nodes {
+        let cfg = RetryConfig {
+            nature_servers: vec!["http://a:8080/".to_string(), "http://b:8080".to_string(), "http://c:8080".to_string()],
+            balance,
+            ..Default::default()
+        };
+        Nodes::from_config(&cfg)
+    }
+
+    #[test]
+    fn from_config_test() {
+        let nodes = Nodes::from_config(&RetryConfig::default());
+        assert_eq!(nodes.nodes.len(), 1);
+        assert_eq!(nodes.nodes[0].redo, "http://localhost:8080/redo_task");
+        assert_eq!(nodes.nodes[0].ready, "http://localhost:8080/ready");
+        assert_eq!(nodes.nodes[0].input, "http://localhost:8080/input");
+        let nodes = three_nodes(Balance::RoundRobin);
+        assert_eq!(nodes.nodes[0].redo, "http://a:8080/redo_task");
+    }
+
+    #[test]
+    fn round_robin_test() {
+        let nodes = three_nodes(Balance::RoundRobin);
+        let picked: Vec<&str> = (0..4).map(|_| nodes.pick("k").unwrap().redo.as_str()).collect();
+        assert_eq!(picked, vec!["http://a:8080/redo_task", "http://b:8080/redo_task", "http://c:8080/redo_task", "http://a:8080/redo_task"]);
+        nodes.nodes[1].set_healthy(false);
+        for _ in 0..4 {
+            assert_ne!(nodes.pick("k").unwrap().redo, "http://b:8080/redo_task");
+        }
+        nodes.nodes[0].set_healthy(false);
+        nodes.nodes[2].set_healthy(false);
+        assert!(nodes.pick("k").is_none());
+    }
+
+    #[test]
+    fn hash_test() {
+        let nodes = three_nodes(Balance::Hash);
+        let first = nodes.pick("B:sale/order:1|1|").unwrap().redo.clone();
+        for _ in 0..4 {
+            assert_eq!(nodes.pick("B:sale/order:1|1|").unwrap().redo, first);
+        }
+    }
+}
@@ -1,51 +1,121 @@
 //! World Connection Service provider
-extern crate dotenv;
-
-use std::env;
+use std::io;
 use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-use actix_web::{App, HttpServer};
+use actix_web::{App, HttpServer, web};
 use actix_web::middleware::Logger;
-use dotenv::dotenv;
 
-use crate::channels::start_receive_threads;
+use crate::channels::{CHANNEL_CONVERT, CHANNEL_DEAD_LETTER, start_receive_threads};
+use crate::common::CONFIG;
 use crate::db::{InstanceDaoImpl, KeyRange};
 use crate::web::actix::*;
+use crate::web::AUTH;
+#[cfg(feature = "tls")]
+use crate::web::TlsSetting;
 
 lazy_static! {
     pub static ref INS_KEY_GT : Arc<dyn KeyRange> = Arc::new(InstanceDaoImpl{});
 }
 
 lazy_static! {
-    pub static ref SERVER_PORT:String={
-    env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string())
-    };
-
-    pub static ref SWITCH_SAVE_DIRECTLY_FOR_ONE : bool = {
-        env::var("SWITCH_SAVE_DIRECTLY_FOR_ONE").unwrap_or_else(|_| "true".to_string()).parse::<bool>().unwrap()
-    };
-
-    pub static ref CACHE_SAVED_TIME : u64 = {
-        env::var("CACHE_SAVED_TIME").unwrap_or_else(|_| "90".to_string()).parse::<u64>().unwrap()
-    };
+    pub static ref SWITCH_SAVE_DIRECTLY_FOR_ONE : bool = CONFIG.nature.switch_save_directly_for_one;
+    pub static ref CACHE_SAVED_TIME : u64 = CONFIG.nature.cache_saved_time;
+    pub static ref STATE_UPDATE_RETRY_TIMES : usize = CONFIG.nature.state_update_retry_times;
 }
 
 pub async fn sys_init() -> std::io::Result<()> {
-    dotenv().ok();
     let _ = env_logger::init();
-    let _ = start_receive_threads();
-    HttpServer::new(|| App::new()
+    if let Err(e) = CONFIG.validate() {
+        error!("configuration error: {}", e);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, e.to_string()));
+    }
+    let setting = &CONFIG.server;
+    if let Err(e) = &*AUTH {
+        error!("auth setting error: {}", e);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, e.to_string()));
+    }
+    let threads = start_receive_threads();
+    let body_limit = setting.body_limit;
+    let mut server = HttpServer::new(move || App::new()
         .wrap(Logger::default())
-        .configure(web_config))
-        .bind("127.0.0.1:".to_owned() + &SERVER_PORT).unwrap()
-        .run().await
+        .app_data(web::PayloadConfig::new(body_limit))
+        .configure(web_config));
+    if setting.workers > 0 {
+        server = server.workers(setting.workers);
+    }
+    server = server.shutdown_timeout(setting.shutdown_timeout);
+    if let Some(keep_alive) = setting.keep_alive {
+        server = server.keep_alive(keep_alive);
+    }
+    #[cfg(feature = "tls")]
+    let tls = match TlsSetting::from_config(setting) {
+        Some(tls) => Some(tls.load().map_err(|e| {
+            error!("load tls error: {}", e);
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        })?),
+        None => None
+    };
+    for address in &setting.bind_addresses() {
+        #[cfg(feature = "tls")]
+        let rtn = match &tls {
+            Some(config) => server.bind_rustls(address, config.clone()),
+            None => server.bind(address)
+        };
+        #[cfg(not(feature = "tls"))]
+        let rtn = server.bind(address);
+        server = match rtn {
+            Ok(s) => s,
+            Err(e) => {
+                let msg = format!("can't bind to {}: {}", address, e);
+                error!("{}", msg);
+                return Err(io::Error::new(e.kind(), msg));
+            }
+        };
+        info!("nature listened on: {}", address);
+    }
+    // actix stops accepting requests and waits the in-flight ones when received SIGTERM, SIGINT or SIGQUIT
+    let rtn = server.run().await;
+    info!("http server stopped, draining the convert queue");
+    let timeout = Duration::from_secs(setting.shutdown_timeout);
+    CHANNEL_CONVERT.stop();
+    match finish_threads(vec![threads.convert], timeout) {
+        true => info!("all the convert threads finished"),
+        false => warn!("convert threads did not finish in {} seconds, the left tasks will be redone by the retry", setting.shutdown_timeout)
+    }
+    // the dead letters generated while draining the convert queue should be handled too
+    CHANNEL_DEAD_LETTER.stop();
+    if !finish_threads(vec![threads.dead_letter], timeout) {
+        warn!("dead letter thread did not finish in {} seconds", setting.shutdown_timeout)
+    }
+    log::logger().flush();
+    rtn
 }
 
-pub fn finish_threads<T>(threads: Vec<JoinHandle<T>>) {
-    for t in threads {
-        let _ = t.join();
-    }
+/// wait all the threads to finish, return false if `timeout` elapsed.
+pub fn finish_threads<T: Send + 'static>(threads: Vec<JoinHandle<T>>, timeout: Duration) -> bool {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for t in threads {
+            let _ = t.join();
+        }
+        let _ = sender.send(());
+    });
+    receiver.recv_timeout(timeout).is_ok()
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
 
+    #[test]
+    fn finish_threads_test() {
+        let quick = vec![thread::spawn(|| 1)];
+        assert!(finish_threads(quick, Duration::from_secs(1)));
+        let slow = vec![thread::spawn(|| thread::sleep(Duration::from_millis(500)))];
+        assert!(!finish_threads(slow, Duration::from_millis(10)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use chrono::Local;
 
 use crate::common::{append_para, CONTEXT_DYNAMIC_PARA, CONTEXT_TARGET_INSTANCE_ID, CONTEXT_TARGET_INSTANCE_PARA, FromInstance, get_para_and_key_from_para, id_from_hex_str, Instance, Meta, MetaType, NatureError, Result};
@@ -91,7 +93,12 @@ fn check_id(ins: &mut Vec<Instance>, from: &FromInstance, target: &Mission) -> R
             ins[0].id = id_from_hex_str(id)?;
         }
         if let Some(para) = target.sys_context.get(CONTEXT_TARGET_INSTANCE_PARA) {
-            ins[0].para = para.to_string();
+            ins[0].para = if target.session {
+                // every session has its own instance
+                append_para(para, &ins[0].para)
+            } else {
+                para.to_string()
+            };
         }
         if ins[0].id == 0 && ins[0].para.is_empty() {
             ins[0].revise()?;
@@ -167,7 +174,7 @@ fn bridge_context_para(instances: &mut Vec<Instance>, mission: &Mission, from: &
 
 fn verify_state(task: &TaskForConvert, instances: &mut Vec<Instance>, last_state: &Option<Instance>) -> Result<()> {
     let to = &task.target.to;
-    if !to.is_state() {
+    if !to.is_state() || instances.is_empty() {
         return Ok(());
     }
     if instances.len() > 1 {
@@ -178,7 +185,9 @@ fn verify_state(task: &TaskForConvert, instances: &mut Vec<Instance>, last_state
 
 // states and state version
     let temp_states = ins.states.clone();
-    match last_state {
+    // the last session of the key is not the same instance as a new session
+    let last = last_state.as_ref().filter(|x| x.para == ins.para);
+    match last {
         None => {
             if task.from.meta == task.target.to.meta_string() {
                 ins.state_version = task.from.state_version + 1;
@@ -187,6 +196,7 @@ fn verify_state(task: &TaskForConvert, instances: &mut Vec<Instance>, last_state
             }
         }
         Some(x) => {
+            to.check_terminal(&x.states)?;
             ins.state_version = x.state_version + 1;
             ins.states = x.states.clone();
         }
@@ -202,7 +212,11 @@ fn verify_state(task: &TaskForConvert, instances: &mut Vec<Instance>, last_state
         }
         ins.states = temp_states
     }
-    Ok(())
+// check transitions
+    match last {
+        None => task.target.to.check_transition(&HashSet::new(), &ins.states),
+        Some(x) => task.target.to.check_transition(&x.states, &ins.states),
+    }
 }
 
 #[cfg(test)]
@@ -261,6 +275,8 @@ mod test {
                 delay: 0,
                 sys_context: Default::default(),
                 id_bridge: false,
+                retry: Default::default(),
+                session: false,
             },
             conflict_version: 0,
         };
@@ -312,6 +328,8 @@ mod test {
                 delay: 0,
                 sys_context: Default::default(),
                 id_bridge: false,
+                retry: Default::default(),
+                session: false,
             },
             conflict_version: 0,
         };
@@ -320,6 +338,93 @@ mod test {
         let one = &ins[0];
         assert_eq!(one.states.contains("new"), true)
     }
+
+    #[test]
+    fn illegal_transition_test() {
+        let mut mission = Mission {
+            to: {
+                let mut m = Meta::from_string("B:hello:1").unwrap();
+                let (ss, _) = State::string_to_states("new,paid").unwrap();
+                let _ = m.set_states(Some(ss));
+                let _ = m.set_setting(r#"{"transitions":{"new":["paid"]}}"#);
+                m
+            },
+            ..Default::default()
+        };
+        mission.target_demand.state_add = vec!["paid".to_string()];
+        let task = TaskForConvert {
+            from: Default::default(),
+            target: mission,
+            conflict_version: 0,
+        };
+        let mut ins = vec![Instance::new("test").unwrap()];
+        let rtn = verify_state(&task, &mut ins, &None);
+        assert!(rtn.is_err());
+
+        let mut last = Instance::new("test").unwrap();
+        last.states.insert("new".to_string());
+        let mut ins = vec![Instance::new("test").unwrap()];
+        let rtn = verify_state(&task, &mut ins, &Some(last));
+        assert!(rtn.is_ok());
+        assert!(ins[0].states.contains("paid"))
+    }
+
+    #[test]
+    fn new_session_test() {
+        let mut mission = Mission {
+            to: {
+                let mut m = Meta::from_string("B:hello:1").unwrap();
+                let (ss, _) = State::string_to_states("new,paid").unwrap();
+                let _ = m.set_states(Some(ss));
+                let _ = m.set_setting(r#"{"transitions":{"new":["paid"]}}"#);
+                m
+            },
+            ..Default::default()
+        };
+        mission.target_demand.state_add = vec!["new".to_string()];
+        let mut task = TaskForConvert {
+            from: Default::default(),
+            target: mission,
+            conflict_version: 0,
+        };
+        // the previous session ended in a non-initial state
+        let mut last = Instance::new("test").unwrap();
+        last.para = "user1/100".to_string();
+        last.state_version = 2;
+        last.states.insert("paid".to_string());
+        let mut ins = vec![Instance::new("test").unwrap()];
+        ins[0].para = "user1/200".to_string();
+        verify_state(&task, &mut ins, &Some(last.clone())).unwrap();
+        assert_eq!(ins[0].state_version, 1);
+        assert_eq!(ins[0].states.len(), 1);
+        assert!(ins[0].states.contains("new"));
+
+        // the transition is checked against the new session only
+        task.target.target_demand.state_add = vec!["paid".to_string()];
+        last.states = ["new".to_string()].iter().cloned().collect();
+        let mut ins = vec![Instance::new("test").unwrap()];
+        ins[0].para = "user1/200".to_string();
+        assert!(verify_state(&task, &mut ins, &Some(last)).is_err());
+    }
+
+    #[test]
+    fn empty_state_instances_test() {
+        let mission = Mission {
+            to: {
+                let mut m = Meta::from_string("B:hello:1").unwrap();
+                let _ = m.set_states(Some(vec![State::Normal("new".to_string())]));
+                m
+            },
+            ..Default::default()
+        };
+        let task = TaskForConvert {
+            from: Default::default(),
+            target: mission,
+            conflict_version: 0,
+        };
+        let rtn = verify_state(&task, &mut vec![], &None);
+        assert!(rtn.is_ok());
+    }
 }
 
 #[cfg(test)]
@@ -4,7 +4,7 @@ use std::ops::Add;
 use chrono::{FixedOffset, Local};
 use futures::Future;
 
-use crate::common::{Instance, KeyCondition, NatureError, Result};
+use crate::common::{CONTEXT_LOOP_FINISHED, CONTEXT_LOOP_NEXT, Instance, KeyCondition, MetaType, NatureError, Result};
 use crate::db::{MetaCache, MetaDao, Mission, MissionRaw, RawTask, TaskType};
 use crate::task::TaskForStore;
 
@@ -65,13 +65,53 @@ impl TaskForConvert {
         let rtn = match result {
             None => return Err(NatureError::EnvironmentError("can't find instance".to_string())),
             Some(ins) => {
-                TaskForConvert {
+                let mut rtn = TaskForConvert {
                     from: ins,
                     target: Mission::from_raw(&mr, mc_g, m_g).await?,
                     conflict_version: 0,
-                }
+                };
+                rtn.loop_cursor();
+                rtn
             }
         };
         Ok(rtn)
     }
+
+    /// the loop cursor saved in the task takes precedence, it may be changed by `/loop/resume`
+    fn loop_cursor(&mut self) {
+        if self.target.to.get_meta_type() != MetaType::Loop {
+            return;
+        }
+        let context = &self.target.sys_context;
+        if let Some(next) = context.get(CONTEXT_LOOP_NEXT) {
+            self.from.sys_context.insert(CONTEXT_LOOP_NEXT.to_string(), next.to_string());
+            if !context.contains_key(CONTEXT_LOOP_FINISHED) {
+                self.from.sys_context.remove(CONTEXT_LOOP_FINISHED);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::Meta;
+
+    use super::*;
+
+    #[test]
+    fn loop_cursor_test() {
+        let mut task = TaskForConvert::default();
+        task.from.sys_context.insert(CONTEXT_LOOP_NEXT.to_string(), "a".to_string());
+        task.from.sys_context.insert(CONTEXT_LOOP_FINISHED.to_string(), "".to_string());
+        task.target.sys_context.insert(CONTEXT_LOOP_NEXT.to_string(), "b".to_string());
+        // not a loop
+        task.target.to = Meta::from_string("B:sale/total:1").unwrap();
+        task.loop_cursor();
+        assert_eq!(task.from.sys_context[CONTEXT_LOOP_NEXT], "a");
+        // resumed
+        task.target.to = Meta::from_string("L:sale/total:1").unwrap();
+        task.loop_cursor();
+        assert_eq!(task.from.sys_context[CONTEXT_LOOP_NEXT], "b");
+        assert!(!task.from.sys_context.contains_key(CONTEXT_LOOP_FINISHED));
+    }
 }
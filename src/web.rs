@@ -1,6 +1,13 @@
 ///! web server, collect data from different web client then call the server
 
+pub use auth::*;
+pub use authed::*;
+pub use server_setting::*;
+
 pub mod actix;
+mod auth;
+mod authed;
+mod server_setting;
 
 
 
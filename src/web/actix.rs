@@ -1,87 +1,165 @@
 use std::fmt::{Display, Formatter};
 
-use actix_web::{HttpResponse, ResponseError, web};
-use actix_web::web::Json;
-use serde::export::fmt::Debug;
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
+use actix_web::http::StatusCode;
+use std::fmt::Debug;
+use serde_json::Value;
 
-use crate::common::{DelayedInstances, Instance, KeyCondition, NatureError, SelfRouteInstance};
-use crate::controller::IncomeController;
+use crate::common::{DelayedInstances, Instance, KeyCondition, LoopResume, NatureError, QueryByMeta, SelfRouteInstance, StateUpdate};
+use crate::controller::{HealthController, IncomeController, LoopController};
 use crate::db::{InstanceDaoImpl, RawTask};
 use crate::system::INS_KEY_GT;
+use crate::web::Authed;
 
 /// **Note** This do not receive System `Meta`'s instances
-async fn input(instance: Json<Instance>) -> HttpResponse {
-    let x = IncomeController::input(instance.0).await;
-    return_result(x)
+async fn input(req: HttpRequest, para: Authed<Instance>) -> HttpResponse {
+    let x = IncomeController::input(para.data).await;
+    return_result(&req, &para.body, x)
 }
 
 /// Instance with route info
-async fn self_route(instance: Json<SelfRouteInstance>) -> HttpResponse {
-    let x = IncomeController::self_route(instance.0).await;
-    return_result(x)
+async fn self_route(req: HttpRequest, para: Authed<SelfRouteInstance>) -> HttpResponse {
+    let x = IncomeController::self_route(para.data).await;
+    return_result(&req, &para.body, x)
 }
 
-async fn callback(delayed: Json<DelayedInstances>) -> HttpResponse {
-    let x = IncomeController::callback(delayed.0).await;
-    return_result(x)
+async fn callback(req: HttpRequest, para: Authed<DelayedInstances>) -> HttpResponse {
+    let x = match para.authorize_task(para.data.task_id).await {
+        Ok(_) => IncomeController::callback(para.data).await,
+        Err(e) => Err(e)
+    };
+    return_result(&req, &para.body, x)
 }
 
-async fn batch(parallel_batch: Json<Vec<Instance>>) -> HttpResponse {
-    let x = IncomeController::batch(parallel_batch.0).await;
-    return_result(x)
+async fn batch(req: HttpRequest, para: Authed<Vec<Instance>>) -> HttpResponse {
+    let x = IncomeController::batch(para.data).await;
+    return_result(&req, &para.body, x)
 }
 
-async fn redo_task(task: Json<RawTask>) -> HttpResponse {
-    let x = IncomeController::redo_task(task.0).await;
-    return_result(x)
+/// change states without a `Relation`
+async fn update_state(req: HttpRequest, para: Authed<StateUpdate>) -> HttpResponse {
+    let x = IncomeController::update_state(para.data).await;
+    return_result(&req, &para.body, x)
+}
+
+async fn redo_task(req: HttpRequest, para: Authed<RawTask>) -> HttpResponse {
+    let x = IncomeController::redo_task(para.data).await;
+    return_result(&req, &para.body, x)
 }
 
 /// exactly query
-async fn get_by_id(para: Json<KeyCondition>) -> HttpResponse {
-    let x = InstanceDaoImpl::get_by_id(para.0).await;
-    return_result(x)
+async fn get_by_id(req: HttpRequest, para: Authed<KeyCondition>) -> HttpResponse {
+    let x = InstanceDaoImpl::get_by_id(para.data).await;
+    return_result(&req, &para.body, x)
 }
 
 /// fuzzy query
-async fn get_by_key_range(para: Json<KeyCondition>) -> HttpResponse {
-    let x = INS_KEY_GT.clone().get_by_key_range(&para.0).await;
-    return_result(x)
+async fn get_by_key_range(req: HttpRequest, para: Authed<KeyCondition>) -> HttpResponse {
+    let x = INS_KEY_GT.clone().get_by_key_range(&para.data).await;
+    return_result(&req, &para.body, x)
+}
+
+/// paging query with conditions
+async fn query_by_meta(req: HttpRequest, para: Authed<QueryByMeta>) -> HttpResponse {
+    let x = InstanceDaoImpl::get_by_meta(&para.data).await;
+    return_result(&req, &para.body, x)
 }
 
-#[derive(Serialize, Deserialize)]
-struct MyStruct {
-    name: String
+/// every version of a state-instance
+async fn state_history(req: HttpRequest, para: Authed<KeyCondition>) -> HttpResponse {
+    let x = InstanceDaoImpl::get_state_history(&para.data).await;
+    return_result(&req, &para.body, x)
 }
 
-// /// fuzzy query
-// async fn hello(para: Json<MyStruct>) -> impl Responder {
-//     format!("Hello {}!", para.name)
-// }
-//
-//
-// #[get("/{id}/{name}/index.html")]
-// async fn index(path: Path<(u32, String)>) -> impl Responder {
-//     format!("Hello {}! id:{}", path.1, path.0)
-// }
+/// unfinished loops, the parameter is the max number of the loops to return
+async fn loop_list(req: HttpRequest, para: Authed<i64>) -> HttpResponse {
+    let x = LoopController::list(para.data).await;
+    return_result(&req, &para.body, x)
+}
 
+/// the parameter is the task_id of the loop
+async fn loop_cancel(req: HttpRequest, para: Authed<u64>) -> HttpResponse {
+    let x = LoopController::cancel(para.data).await;
+    return_result(&req, &para.body, x)
+}
+
+async fn loop_resume(req: HttpRequest, para: Authed<LoopResume>) -> HttpResponse {
+    let x = LoopController::resume(para.data).await;
+    return_result(&req, &para.body, x)
+}
+
+/// the process is alive
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+/// whether the node can accept requests, `503` will be returned if not.
+async fn ready() -> HttpResponse {
+    let rtn = HealthController::ready().await;
+    match rtn.ready {
+        true => HttpResponse::Ok().json(rtn),
+        false => HttpResponse::ServiceUnavailable().json(rtn)
+    }
+}
 
 pub fn web_config(cfg: &mut web::ServiceConfig) {
+    // v2 use the http status code for errors, see `WebError`
+    cfg.service(web::scope(V2).configure(routes));
+    // for load balancers, need no authentication
+    cfg.route("/health", web::get().to(health))
+        .route("/ready", web::get().to(ready));
+    routes(cfg);
+}
+
+fn routes(cfg: &mut web::ServiceConfig) {
     cfg
-        // .service(index)
-        // .route("/hello", web::post().to(hello))
         .route("/input", web::post().to(input))
         .route("/self_route", web::post().to(self_route))
         .route("/callback", web::post().to(callback))
         .route("/batch", web::post().to(batch))
+        .route("/update_state", web::post().to(update_state))
         .route("/redo_task", web::post().to(redo_task))
         .route("/get_by_id", web::post().to(get_by_id))
-        .route("/get_by_key_range", web::post().to(get_by_key_range));
+        .route("/get_by_key_range", web::post().to(get_by_key_range))
+        .route("/query_by_meta", web::post().to(query_by_meta))
+        .route("/state_history", web::post().to(state_history))
+        .route("/loop/list", web::post().to(loop_list))
+        .route("/loop/cancel", web::post().to(loop_cancel))
+        .route("/loop/resume", web::post().to(loop_resume));
 }
 
 
+static V2: &str = "/v2";
+
+/// the error responded in the format of the interface version
 #[derive(Debug)]
-struct WebError {
-    err: NatureError,
+pub(crate) struct WebError {
+    pub(crate) err: NatureError,
+    /// key of the task or the instance which the request is for
+    key: Option<String>,
+    v2: bool,
+    /// the body can't be deserialized, v1 responds `400` for it as the `Json` extractor did
+    bad_body: bool,
+}
+
+impl WebError {
+    pub(crate) fn new(req: &HttpRequest, err: NatureError, key: Option<String>) -> Self {
+        WebError { err, key, v2: req.path().starts_with(V2), bad_body: false }
+    }
+
+    pub(crate) fn bad_body(req: &HttpRequest, err: NatureError, key: Option<String>) -> Self {
+        WebError { bad_body: true, ..Self::new(req, err, key) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ErrorBody {
+    /// name of the `NatureError`, for example "VerifyError"
+    code: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    key: Option<String>,
 }
 
 impl Display for WebError {
@@ -90,10 +168,111 @@ impl Display for WebError {
     }
 }
 
-impl ResponseError for WebError {}
+impl ResponseError for WebError {
+    fn status_code(&self) -> StatusCode {
+        if !self.v2 {
+            return match self.bad_body {
+                true => StatusCode::BAD_REQUEST,
+                false => StatusCode::OK
+            };
+        }
+        match self.err {
+            NatureError::VerifyError(_) => StatusCode::BAD_REQUEST,
+            NatureError::DaoDuplicated(_) => StatusCode::CONFLICT,
+            NatureError::EnvironmentError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if !self.v2 {
+            return match self.bad_body {
+                true => HttpResponse::BadRequest().body(self.err.to_string()),
+                false => HttpResponse::Ok().json(Err::<(), _>(&self.err))
+            };
+        }
+        let (code, message) = match &self.err {
+            NatureError::VerifyError(m) => ("VerifyError", m),
+            NatureError::LogicalError(m) => ("LogicalError", m),
+            NatureError::DaoDuplicated(m) => ("DaoDuplicated", m),
+            NatureError::SystemError(m) => ("SystemError", m),
+            NatureError::EnvironmentError(m) => ("EnvironmentError", m),
+        };
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            code: code.to_string(),
+            message: message.to_string(),
+            key: self.key.clone(),
+        })
+    }
+}
 
-fn return_result<T>(x: crate::common::Result<T>) -> HttpResponse
+/// v1 always returns `200 OK` with the serialized `Result`,
+/// v2 returns the value directly, or the `ErrorBody` with the http status code.
+fn return_result<T>(req: &HttpRequest, body: &[u8], x: crate::common::Result<T>) -> HttpResponse
     where T: serde::Serialize + Debug
 {
-    HttpResponse::Ok().json(x)
+    match x {
+        Ok(v) if req.path().starts_with(V2) => HttpResponse::Ok().json(v),
+        Ok(v) => HttpResponse::Ok().json(Ok::<_, ()>(v)),
+        Err(err) => WebError::new(req, err, request_key(body)).error_response()
+    }
+}
+
+/// get the task key or the instance key from the request body
+pub(crate) fn request_key(body: &[u8]) -> Option<String> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let value = value.get("instance").unwrap_or(&value);
+    if let Some(key) = value.get("task_key").and_then(|v| v.as_str()) {
+        return Some(key.to_string());
+    }
+    if let Some(id) = value.get("task_id").and_then(|v| v.as_u64()) {
+        return Some(id.to_string());
+    }
+    let meta = value.get("meta")?.as_str()?;
+    let id = value.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+    let para = value.get("para").and_then(|v| v.as_str()).unwrap_or("");
+    Some(KeyCondition::new(id, meta, para, 0).get_key())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn status_test() {
+        let status = |err: NatureError| WebError { err, key: None, v2: true, bad_body: false }.status_code();
+        assert_eq!(status(NatureError::VerifyError("".to_string())), StatusCode::BAD_REQUEST);
+        assert_eq!(status(NatureError::DaoDuplicated("".to_string())), StatusCode::CONFLICT);
+        assert_eq!(status(NatureError::EnvironmentError("".to_string())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(NatureError::LogicalError("".to_string())), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(status(NatureError::SystemError("".to_string())), StatusCode::INTERNAL_SERVER_ERROR);
+        // v1 keeps `400` for the body which can't be deserialized
+        let req = TestRequest::with_uri("/input").to_http_request();
+        let err = NatureError::VerifyError("".to_string());
+        assert_eq!(WebError::bad_body(&req, err.clone(), None).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(WebError::new(&req, err, None).status_code(), StatusCode::OK);
+    }
+
+    #[test]
+    fn return_result_test() {
+        let body = br#"{"meta":"B:sale/order:1","id":5,"para":"a"}"#;
+        let err: crate::common::Result<u64> = Err(NatureError::VerifyError("bad".to_string()));
+        let req = TestRequest::with_uri("/input").to_http_request();
+        assert_eq!(return_result(&req, body, err.clone()).status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/v2/input").to_http_request();
+        assert_eq!(return_result(&req, body, err).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(return_result(&req, body, Ok(5)).status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn request_key_test() {
+        assert_eq!(request_key(br#"{"meta":"B:sale/order:1","id":5,"para":"a"}"#), Some("B:sale/order:1|5|a".to_string()));
+        assert_eq!(request_key(br#"{"instance":{"meta":"B:sale/order:1"}}"#), Some("B:sale/order:1|0|".to_string()));
+        assert_eq!(request_key(br#"{"task_key":"B:a:1|0||0","task_id":3}"#), Some("B:a:1|0||0".to_string()));
+        assert_eq!(request_key(br#"{"task_id":3}"#), Some("3".to_string()));
+        assert_eq!(request_key(b"100"), None);
+        assert_eq!(request_key(b"[]"), None);
+    }
 }
@@ -0,0 +1,271 @@
+//! authenticate the clients by static API keys or HMAC signed requests, and authorize them by `Meta`s.
+//!
+//! Authentication is disabled unless `AUTH_CLIENTS_FILE` is set, the file is a json array of `Client`.
+//! Other schemes can be added by implementing `Authenticator`.
+use std::collections::HashMap;
+use std::fs;
+
+use actix_web::HttpRequest;
+use chrono::Local;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::common::{CONFIG, is_default, NatureError, Result};
+
+pub static HEADER_API_KEY: &str = "X-Api-Key";
+pub static HEADER_TIMESTAMP: &str = "X-Timestamp";
+pub static HEADER_SIGNATURE: &str = "X-Signature";
+
+lazy_static! {
+    pub static ref AUTH: Result<Box<dyn Authenticator>> = Auth::from_config().map(|a| Box::new(a) as Box<dyn Authenticator>);
+}
+
+/// find out who sent the request, it runs before the body is deserialized.
+pub trait Authenticator: Send + Sync {
+    /// `body`: the raw request body. `Ok(None)` means authentication is disabled.
+    fn authenticate(&self, req: &HttpRequest, body: &[u8]) -> Result<Option<Client>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    Input,
+    Query,
+    /// `/redo_task`, `/loop/*` and so on
+    Admin,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Client {
+    pub key: String,
+    /// if set, the request must be signed, `X-Signature` is the hex of
+    /// HMAC-SHA256(secret, method + "\n" + path + "\n" + X-Timestamp + "\n" + body)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// `Meta`s can be input, end with "*" to match the prefix, for example "B:sale/*"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub input: Vec<String>,
+    /// `Meta`s can be queried, the same format as `input`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub query: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl Client {
+    /// `meta`: None means the operation is not limited to a `Meta`, any permission of the kind is enough.
+    pub fn check(&self, permission: Permission, meta: Option<&str>) -> Result<()> {
+        let patterns = match permission {
+            Permission::Admin => return match self.admin {
+                true => Ok(()),
+                false => Err(NatureError::VerifyError(format!("client {} has no admin permission", self.key)))
+            },
+            Permission::Input => &self.input,
+            Permission::Query => &self.query,
+        };
+        let allowed = match meta {
+            None => !patterns.is_empty(),
+            Some(meta) => patterns.iter().any(|p| matched(p, meta)),
+        };
+        match allowed || self.admin {
+            true => Ok(()),
+            false => Err(NatureError::VerifyError(format!("client {} has no {:?} permission for: {}", self.key, permission, meta.unwrap_or(""))))
+        }
+    }
+
+    /// check all the `Meta`s, empty `metas` means the operation is not limited to a `Meta`.
+    pub fn authorize(&self, permission: Permission, metas: &[String]) -> Result<()> {
+        if metas.is_empty() {
+            return self.check(permission, None);
+        }
+        for meta in metas {
+            self.check(permission, Some(meta))?;
+        }
+        Ok(())
+    }
+
+    fn verify(&self, req: &HttpRequest, timestamp: Option<&str>, signature: Option<&str>, body: &[u8]) -> Result<()> {
+        let secret = match &self.secret {
+            None => return Ok(()),
+            Some(s) => s
+        };
+        let (timestamp, signature) = match (timestamp, signature) {
+            (Some(t), Some(s)) => (t, s),
+            _ => return Err(NatureError::VerifyError(format!("{} and {} are needed", HEADER_TIMESTAMP, HEADER_SIGNATURE)))
+        };
+        let time = timestamp.parse::<i64>().map_err(|_| NatureError::VerifyError(format!("illegal {}", HEADER_TIMESTAMP)))?;
+        if (Local::now().timestamp() - time).abs() > CONFIG.auth.max_skew {
+            return Err(NatureError::VerifyError("request expired".to_string()));
+        }
+        let signature = from_hex(signature).ok_or_else(|| NatureError::VerifyError(format!("illegal {}", HEADER_SIGNATURE)))?;
+        let mac = mac(secret, req.method().as_str(), req.path(), timestamp, body)?;
+        mac.verify(&signature).map_err(|_| NatureError::VerifyError("signature mismatched".to_string()))
+    }
+}
+
+/// authenticate by `X-Api-Key`, and verify the `X-Signature` if the client has a secret.
+pub struct Auth {
+    /// None means authentication is disabled
+    clients: Option<HashMap<String, Client>>,
+}
+
+impl Auth {
+    fn from_config() -> Result<Self> {
+        let file = match &CONFIG.auth.clients_file {
+            Some(file) => file,
+            None => return Ok(Auth { clients: None })
+        };
+        let json = fs::read_to_string(file)
+            .map_err(|e| NatureError::VerifyError(format!("can't read AUTH_CLIENTS_FILE {}: {}", file, e)))?;
+        Self::from_json(&json)
+    }
+
+    pub(crate) fn from_json(json: &str) -> Result<Self> {
+        let list: Vec<Client> = serde_json::from_str(json)?;
+        let mut clients = HashMap::new();
+        for one in list {
+            if one.key.is_empty() {
+                return Err(NatureError::VerifyError("client key can't be empty".to_string()));
+            }
+            if let Some(old) = clients.insert(one.key.clone(), one) {
+                return Err(NatureError::VerifyError(format!("client key repeated: {}", old.key)));
+            }
+        }
+        Ok(Auth { clients: Some(clients) })
+    }
+}
+
+impl Authenticator for Auth {
+    fn authenticate(&self, req: &HttpRequest, body: &[u8]) -> Result<Option<Client>> {
+        let clients = match &self.clients {
+            None => return Ok(None),
+            Some(c) => c
+        };
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        let client = match header(HEADER_API_KEY).and_then(|key| clients.get(key)) {
+            Some(c) => c,
+            None => return Err(NatureError::VerifyError("unknown client".to_string()))
+        };
+        client.verify(req, header(HEADER_TIMESTAMP), header(HEADER_SIGNATURE), body)?;
+        Ok(Some(client.clone()))
+    }
+}
+
+/// the hex of the signature, used by the client to sign the request.
+/// `path` is the path of the url without the query, for example "/v2/input"
+pub fn sign(secret: &str, method: &str, path: &str, timestamp: &str, body: &[u8]) -> Result<String> {
+    let mac = mac(secret, method, path, timestamp, body)?;
+    Ok(mac.result().code().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// the method and the path are signed too, so that the request can't be replayed to another interface
+fn mac(secret: &str, method: &str, path: &str, timestamp: &str, body: &[u8]) -> Result<Hmac<Sha256>> {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).map_err(|e| NatureError::SystemError(format!("{:?}", e)))?;
+    for part in &[method.as_bytes(), path.as_bytes(), timestamp.as_bytes()] {
+        mac.input(part);
+        mac.input(b"\n");
+    }
+    mac.input(body);
+    Ok(mac)
+}
+
+fn matched(pattern: &str, meta: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => meta.starts_with(prefix),
+        None => pattern == meta
+    }
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn auth() -> Auth {
+        Auth::from_json(r#"[
+            {"key":"k1","input":["B:sale/*"],"query":["B:sale/order:1"]},
+            {"key":"k2","secret":"s2","admin":true}
+        ]"#).unwrap()
+    }
+
+    fn check(auth: &Auth, req: &HttpRequest, body: &[u8], permission: Permission, metas: &[String]) -> Result<()> {
+        match auth.authenticate(req, body)? {
+            Some(client) => client.authorize(permission, metas),
+            None => Ok(())
+        }
+    }
+
+    #[test]
+    fn disabled_test() {
+        let auth = Auth { clients: None };
+        let req = TestRequest::default().to_http_request();
+        assert!(check(&auth, &req, b"", Permission::Admin, &[]).is_ok());
+    }
+
+    #[test]
+    fn api_key_test() {
+        let auth = auth();
+        let req = TestRequest::default().header(HEADER_API_KEY, "k1").to_http_request();
+        assert!(check(&auth, &req, b"", Permission::Input, &["B:sale/order:1".to_string()]).is_ok());
+        assert!(check(&auth, &req, b"", Permission::Input, &["B:sale/order:1".to_string(), "B:user:1".to_string()]).is_err());
+        assert!(check(&auth, &req, b"", Permission::Query, &["B:sale/item:1".to_string()]).is_err());
+        assert!(check(&auth, &req, b"", Permission::Query, &[]).is_ok());
+        assert!(check(&auth, &req, b"", Permission::Admin, &[]).is_err());
+        let req = TestRequest::default().header(HEADER_API_KEY, "none").to_http_request();
+        assert_eq!(check(&auth, &req, b"", Permission::Query, &[]).err().unwrap(), NatureError::VerifyError("unknown client".to_string()));
+        let req = TestRequest::default().to_http_request();
+        assert!(check(&auth, &req, b"", Permission::Query, &[]).is_err());
+    }
+
+    #[test]
+    fn hmac_test() {
+        let auth = auth();
+        let body = br#"{"task_id":1}"#;
+        let now = Local::now().timestamp().to_string();
+        let signature = sign("s2", "POST", "/redo_task", &now, body).unwrap();
+        let req = TestRequest::post().uri("/redo_task")
+            .header(HEADER_API_KEY, "k2")
+            .header(HEADER_TIMESTAMP, now.as_str())
+            .header(HEADER_SIGNATURE, signature.as_str())
+            .to_http_request();
+        assert!(check(&auth, &req, body, Permission::Admin, &[]).is_ok());
+        assert!(check(&auth, &req, b"changed", Permission::Admin, &[]).is_err());
+        // replayed to another interface
+        let req = TestRequest::post().uri("/v2/redo_task")
+            .header(HEADER_API_KEY, "k2")
+            .header(HEADER_TIMESTAMP, now.as_str())
+            .header(HEADER_SIGNATURE, signature.as_str())
+            .to_http_request();
+        assert!(check(&auth, &req, body, Permission::Admin, &[]).is_err());
+        // expired
+        let old = (Local::now().timestamp() - 3600).to_string();
+        let req = TestRequest::post().uri("/redo_task")
+            .header(HEADER_API_KEY, "k2")
+            .header(HEADER_TIMESTAMP, old.as_str())
+            .header(HEADER_SIGNATURE, sign("s2", "POST", "/redo_task", &old, body).unwrap().as_str())
+            .to_http_request();
+        assert!(check(&auth, &req, body, Permission::Admin, &[]).is_err());
+        // no signature
+        let req = TestRequest::default().header(HEADER_API_KEY, "k2").to_http_request();
+        assert!(check(&auth, &req, body, Permission::Admin, &[]).is_err());
+    }
+
+    #[test]
+    fn config_test() {
+        assert!(Auth::from_json(r#"[{"key":"a"},{"key":"a"}]"#).is_err());
+        assert!(Auth::from_json(r#"[{"key":""}]"#).is_err());
+        assert_eq!(from_hex("0aFf"), Some(vec![10, 255]));
+        assert_eq!(from_hex("0g"), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! extract the request body only after the client is authenticated.
+use actix_web::{dev, FromRequest, HttpRequest};
+use actix_web::web::Bytes;
+use futures::future::{FutureExt, LocalBoxFuture};
+use serde::de::DeserializeOwned;
+
+use crate::common::{ConverterReturned, DelayedInstances, Instance, KeyCondition, LoopResume, QueryByMeta, Result, SelfRouteInstance, StateUpdate};
+use crate::db::{D_T, RawTask, TaskDao};
+use crate::web::{AUTH, Authenticator, Client, Permission};
+use crate::web::actix::{request_key, WebError};
+
+/// the permission needed by a request body
+pub trait Guarded {
+    fn permission() -> Permission;
+
+    /// `Meta`s the request is for, empty means the operation is not limited to a `Meta`.
+    fn metas(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// The request body of type `T`.
+///
+/// The raw body is authenticated by the `AUTH` first, then deserialized,
+/// and at last the client is authorized for the `Meta`s of `T`.
+pub struct Authed<T> {
+    pub data: T,
+    /// the raw body, used to find the key for the error
+    pub body: Bytes,
+    /// None means authentication is disabled
+    pub client: Option<Client>,
+}
+
+impl<T: Guarded> Authed<T> {
+    /// authorize the client for the target `Meta` of the task too
+    pub async fn authorize_task(&self, task_id: u64) -> Result<()> {
+        let client = match &self.client {
+            None => return Ok(()),
+            Some(c) => c
+        };
+        match D_T.get(&task_id).await? {
+            Some(raw) => client.authorize(T::permission(), &[raw.task_for]),
+            // reported by the handler
+            None => Ok(())
+        }
+    }
+}
+
+impl<T> FromRequest for Authed<T> where T: DeserializeOwned + Guarded + 'static {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, std::result::Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut dev::Payload) -> Self::Future {
+        let req = req.clone();
+        let body = Bytes::from_request(&req, payload);
+        async move {
+            let body = body.await?;
+            let (data, client) = match &*AUTH {
+                Ok(auth) => guard(auth.as_ref(), &req, &body)?,
+                Err(e) => return Err(WebError::new(&req, e.clone(), None).into())
+            };
+            Ok(Authed { data, body, client })
+        }.boxed_local()
+    }
+}
+
+/// authenticate the raw body, then deserialize it, and then authorize the client for the `Meta`s of it.
+fn guard<T>(auth: &dyn Authenticator, req: &HttpRequest, body: &[u8]) -> std::result::Result<(T, Option<Client>), WebError>
+    where T: DeserializeOwned + Guarded
+{
+    // nothing about the body is responded before authenticated
+    let client = auth.authenticate(req, body).map_err(|err| WebError::new(req, err, None))?;
+    let data: T = serde_json::from_slice(body).map_err(|e| WebError::bad_body(req, e.into(), request_key(body)))?;
+    if let Some(client) = &client {
+        client.authorize(T::permission(), &data.metas()).map_err(|err| WebError::new(req, err, request_key(body)))?;
+    }
+    Ok((data, client))
+}
+
+impl Guarded for Instance {
+    fn permission() -> Permission { Permission::Input }
+
+    fn metas(&self) -> Vec<String> {
+        vec![self.meta.clone()]
+    }
+}
+
+impl Guarded for SelfRouteInstance {
+    fn permission() -> Permission { Permission::Input }
+
+    fn metas(&self) -> Vec<String> {
+        vec![self.instance.meta.clone()]
+    }
+}
+
+impl Guarded for Vec<Instance> {
+    fn permission() -> Permission { Permission::Input }
+
+    fn metas(&self) -> Vec<String> {
+        self.iter().map(|i| i.meta.clone()).collect()
+    }
+}
+
+/// the target `Meta` of the task is authorized by the handler
+impl Guarded for DelayedInstances {
+    fn permission() -> Permission { Permission::Input }
+
+    fn metas(&self) -> Vec<String> {
+        let metas = match &self.result {
+            ConverterReturned::Instances { ins } => ins.iter().map(|i| i.meta.clone()).collect(),
+            ConverterReturned::SelfRoute { ins } => ins.iter().map(|i| i.instance.meta.clone()).collect(),
+            _ => vec![]
+        };
+        // the instances without `Meta` are output to the target of the task
+        metas.into_iter().filter(|m: &String| !m.is_empty()).collect()
+    }
+}
+
+impl Guarded for StateUpdate {
+    fn permission() -> Permission { Permission::Input }
+
+    fn metas(&self) -> Vec<String> {
+        vec![self.meta.clone()]
+    }
+}
+
+impl Guarded for KeyCondition {
+    fn permission() -> Permission { Permission::Query }
+
+    fn metas(&self) -> Vec<String> {
+        vec![self.meta.clone()]
+    }
+}
+
+impl Guarded for QueryByMeta {
+    fn permission() -> Permission { Permission::Query }
+
+    fn metas(&self) -> Vec<String> {
+        vec![self.meta.clone()]
+    }
+}
+
+impl Guarded for RawTask {
+    fn permission() -> Permission { Permission::Admin }
+}
+
+impl Guarded for LoopResume {
+    fn permission() -> Permission { Permission::Admin }
+}
+
+/// the limit of `/loop/list`
+impl Guarded for i64 {
+    fn permission() -> Permission { Permission::Admin }
+}
+
+/// the task_id of `/loop/cancel`
+impl Guarded for u64 {
+    fn permission() -> Permission { Permission::Admin }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use crate::common::NatureError;
+    use crate::web::{Auth, HEADER_API_KEY};
+
+    use super::*;
+
+    #[test]
+    fn guard_test() {
+        let auth = Auth::from_json(r#"[{"key":"k1","input":["B:sale/*"]}]"#).unwrap();
+        // not authenticated, the body is not parsed
+        let req = TestRequest::with_uri("/input").to_http_request();
+        let rtn = guard::<Instance>(&auth, &req, b"not json");
+        assert_eq!(rtn.err().unwrap().err, NatureError::VerifyError("unknown client".to_string()));
+        // authenticated, but illegal body or not authorized
+        let req = TestRequest::with_uri("/input").header(HEADER_API_KEY, "k1").to_http_request();
+        assert!(guard::<Instance>(&auth, &req, b"not json").is_err());
+        let mut ins = Instance::default();
+        ins.meta = "B:user:1".to_string();
+        assert!(guard::<Instance>(&auth, &req, &serde_json::to_vec(&ins).unwrap()).is_err());
+        let kc = KeyCondition::new(0, "B:sale/order:1", "", 0);
+        assert!(guard::<KeyCondition>(&auth, &req, &serde_json::to_vec(&kc).unwrap()).is_err());
+        // ok
+        ins.meta = "B:sale/order:1".to_string();
+        let (rtn, client) = guard::<Instance>(&auth, &req, &serde_json::to_vec(&ins).unwrap()).unwrap();
+        assert_eq!(rtn.meta, "B:sale/order:1");
+        assert_eq!(client.unwrap().key, "k1");
+        // callback
+        let mut delayed = DelayedInstances { task_id: 1, result: ConverterReturned::Instances { ins: vec![ins.clone(), Instance::default()] } };
+        assert!(guard::<DelayedInstances>(&auth, &req, &serde_json::to_vec(&delayed).unwrap()).is_ok());
+        ins.meta = "B:user:1".to_string();
+        delayed.result = ConverterReturned::Instances { ins: vec![ins] };
+        assert!(guard::<DelayedInstances>(&auth, &req, &serde_json::to_vec(&delayed).unwrap()).is_err());
+    }
+}
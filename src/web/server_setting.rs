@@ -0,0 +1,58 @@
+#[cfg(feature = "tls")]
+use crate::common::{NatureError, Result};
+use crate::common::ServerConfig;
+
+/// pem files for https, need the `tls` feature
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsSetting {
+    pub cert: String,
+    pub key: String,
+}
+
+impl TlsSetting {
+    /// `Config::validate` makes sure the cert and the key are set together
+    pub fn from_config(cfg: &ServerConfig) -> Option<Self> {
+        match (&cfg.tls_cert, &cfg.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsSetting { cert: cert.clone(), key: key.clone() }),
+            _ => None
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl TlsSetting {
+    pub fn load(&self) -> Result<rustls::ServerConfig> {
+        use std::fs::File;
+        use std::io::BufReader;
+
+        use rustls::{NoClientAuth, ServerConfig};
+        use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+
+        let err = |msg: &str| NatureError::VerifyError(format!("{}: {} {}", msg, self.cert, self.key));
+        let certs = certs(&mut BufReader::new(File::open(&self.cert)?)).map_err(|_| err("can't read cert"))?;
+        let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&self.key)?)).map_err(|_| err("can't read key"))?;
+        if keys.is_empty() {
+            keys = rsa_private_keys(&mut BufReader::new(File::open(&self.key)?)).map_err(|_| err("can't read key"))?;
+        }
+        if keys.is_empty() {
+            return Err(err("no private key found"));
+        }
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config.set_single_cert(certs, keys.remove(0)).map_err(|e| err(&e.to_string()))?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_config_test() {
+        let mut cfg = ServerConfig::default();
+        assert_eq!(TlsSetting::from_config(&cfg), None);
+        cfg.tls_cert = Some("cert.pem".to_string());
+        cfg.tls_key = Some("key.pem".to_string());
+        assert_eq!(TlsSetting::from_config(&cfg), Some(TlsSetting { cert: "cert.pem".to_string(), key: "key.pem".to_string() }));
+    }
+}